@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{BondingCurve, ErrorCode};
+
+/// Prefixes that keep a leaf from being passed off as an inner node
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Presale phase from creation until `ends_at` (unix time) in which only
+/// wallets in the merkle tree under `merkle_root` may buy
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct AllowlistPhase {
+    pub merkle_root: [u8; 32],
+    pub ends_at: i64,
+}
+
+/// A buyer's leaf in the allowlist tree and the sibling hashes from it up
+/// to the root
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AllowlistProof {
+    /// Most tokens the wallet may buy during the phase, if capped
+    pub allocation: Option<u64>,
+    pub proof: Vec<[u8; 32]>,
+}
+
+impl AllowlistPhase {
+    pub fn validate(&self, now: i64) -> Result<()> {
+        require!(self.ends_at > now, ErrorCode::InvalidAllowlist);
+        Ok(())
+    }
+
+    /// Leaf for `wallet`: sha256 of 0x00, the wallet and, if capped, its
+    /// allocation as little-endian u64
+    pub fn leaf(wallet: &Pubkey, allocation: Option<u64>) -> [u8; 32] {
+        match allocation {
+            Some(allocation) => hashv(&[LEAF_PREFIX, wallet.as_ref(), &allocation.to_le_bytes()]),
+            None => hashv(&[LEAF_PREFIX, wallet.as_ref()]),
+        }
+        .to_bytes()
+    }
+
+    /// Whether `proof` leads from `leaf` to the root. Inner nodes are sha256
+    /// of 0x01 and their two children in ascending order, so proofs don't
+    /// need left/right flags.
+    pub fn verify(&self, leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+        let root = proof.iter().fold(leaf, |node, sibling| {
+            let (low, high) = if node <= *sibling { (&node, sibling) } else { (sibling, &node) };
+            hashv(&[NODE_PREFIX, low, high]).to_bytes()
+        });
+        root == self.merkle_root
+    }
+}
+
+impl BondingCurve {
+    /// The allowlist phase, while it still runs at unix time `now`
+    pub fn active_allowlist(&self, now: i64) -> Option<&AllowlistPhase> {
+        self.allowlist.as_ref().filter(|phase| now < phase.ends_at)
+    }
+
+    /// Check that `buyer` may buy at `now` and return its allocation, if
+    /// the allowlist phase runs and caps it
+    pub fn check_allowlist(
+        &self,
+        now: i64,
+        buyer: &Pubkey,
+        proof: Option<&AllowlistProof>,
+    ) -> Result<Option<u64>> {
+        let phase = match self.active_allowlist(now) {
+            Some(phase) => phase,
+            None => return Ok(None),
+        };
+        let proof = proof.ok_or(ErrorCode::AllowlistProofRequired)?;
+        require!(
+            phase.verify(AllowlistPhase::leaf(buyer, proof.allocation), &proof.proof),
+            ErrorCode::NotAllowlisted
+        );
+        Ok(proof.allocation)
+    }
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+
+use crate::fees::{self, FeeSplit};
+use crate::{ErrorCode, PlatformConfig};
+
+/// The graduation program, whose `["graduation_authority"]` PDA signs
+/// `graduate` and `withdraw_reserves`
+pub mod graduation_program {
+    use anchor_lang::prelude::*;
+
+    declare_id!("BMH2GPLn8woVeGFKAHwJ3wPpBf7mhxRipPzPm9d6Pbjt");
+}
+
+pub const GRADUATION_AUTHORITY_SEED: &[u8] = b"graduation_authority";
+
+/// The graduation program's signing PDA, derived once at
+/// `initialize_config` and kept in the platform config
+pub fn graduation_authority() -> Pubkey {
+    Pubkey::find_program_address(&[GRADUATION_AUTHORITY_SEED], &graduation_program::ID).0
+}
+
+/// Platform parameters set by `initialize_config`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConfigParams {
+    pub max_fee_bps: u16,
+    pub creator_fee_share_bps: u16,
+    pub referrer_fee_share_bps: u16,
+    pub post_graduation_fee_bps: u16,
+    pub graduation_threshold: u64,
+    pub min_base_price: u64,
+    pub max_base_price: u64,
+}
+
+/// Fields to change in `update_config`; `None` leaves a field as it is
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigUpdate {
+    pub admin: Option<Pubkey>,
+    pub max_fee_bps: Option<u16>,
+    pub creator_fee_share_bps: Option<u16>,
+    pub referrer_fee_share_bps: Option<u16>,
+    pub post_graduation_fee_bps: Option<u16>,
+    pub graduation_threshold: Option<u64>,
+    pub min_base_price: Option<u64>,
+    pub max_base_price: Option<u64>,
+    pub paused: Option<bool>,
+}
+
+impl PlatformConfig {
+    /// Check the parameters every curve relies on
+    pub fn validate(&self) -> Result<()> {
+        require!(self.max_fee_bps <= fees::MAX_FEE_BPS, ErrorCode::InvalidConfig);
+        require!(
+            self.creator_fee_share_bps as u64 + self.referrer_fee_share_bps as u64 <= fees::BPS_DENOMINATOR,
+            ErrorCode::InvalidConfig
+        );
+        require!(self.post_graduation_fee_bps <= fees::MAX_FEE_BPS, ErrorCode::InvalidConfig);
+        require!(self.graduation_threshold > 0, ErrorCode::InvalidConfig);
+        require!(self.min_base_price > 0, ErrorCode::InvalidConfig);
+        require!(self.min_base_price <= self.max_base_price, ErrorCode::InvalidConfig);
+        Ok(())
+    }
+
+    pub fn apply(&mut self, update: &ConfigUpdate) -> Result<()> {
+        if let Some(admin) = update.admin {
+            self.admin = admin;
+        }
+        if let Some(max_fee_bps) = update.max_fee_bps {
+            self.max_fee_bps = max_fee_bps;
+        }
+        if let Some(creator_fee_share_bps) = update.creator_fee_share_bps {
+            self.creator_fee_share_bps = creator_fee_share_bps;
+        }
+        if let Some(referrer_fee_share_bps) = update.referrer_fee_share_bps {
+            self.referrer_fee_share_bps = referrer_fee_share_bps;
+        }
+        if let Some(post_graduation_fee_bps) = update.post_graduation_fee_bps {
+            self.post_graduation_fee_bps = post_graduation_fee_bps;
+        }
+        if let Some(graduation_threshold) = update.graduation_threshold {
+            self.graduation_threshold = graduation_threshold;
+        }
+        if let Some(min_base_price) = update.min_base_price {
+            self.min_base_price = min_base_price;
+        }
+        if let Some(max_base_price) = update.max_base_price {
+            self.max_base_price = max_base_price;
+        }
+        if let Some(paused) = update.paused {
+            self.paused = paused;
+        }
+        self.validate()
+    }
+
+    /// Only the graduation program's PDA or the admin may graduate a curve
+    /// or release its reserves
+    pub fn is_graduation_handler(&self, handler: &Pubkey) -> bool {
+        *handler == self.graduation_authority || *handler == self.admin
+    }
+
+    /// Divide a trade's fee; the referrer share stays with the platform when
+    /// the trade has no referrer
+    pub fn split_fee(&self, fee: u64, has_referrer: bool) -> Result<FeeSplit> {
+        let referrer_share_bps = if has_referrer { self.referrer_fee_share_bps } else { 0 };
+        FeeSplit::new(fee, self.creator_fee_share_bps, referrer_share_bps)
+    }
+
+    /// Trading and curve creation are blocked while paused
+    pub fn require_active(&self) -> Result<()> {
+        require!(!self.paused, ErrorCode::PlatformPaused);
+        Ok(())
+    }
+}
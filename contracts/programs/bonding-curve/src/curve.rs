@@ -0,0 +1,968 @@
+use anchor_lang::prelude::*;
+
+use crate::allowlist::AllowlistPhase;
+use crate::launch_fee::LaunchFeeSchedule;
+use crate::launch_limits::LaunchLimits;
+use crate::math::{to_u128, to_u64, U256};
+use crate::{BondingCurve, CurveConfig, CurveSegment, ErrorCode};
+
+/// Most times an exponential curve may double its price before max_supply
+pub const MAX_DOUBLINGS: u64 = 32;
+
+/// Most segments a piecewise curve config may hold
+pub const MAX_CURVE_SEGMENTS: usize = 16;
+
+/// Sqrt price bounds (Q64.64) accepted for piecewise curves, same as
+/// Meteora DBC
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_521_066_979_257_578_248_091;
+
+/// Per-curve parameters chosen by the creator in `initialize_curve`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CurveParams {
+    pub base_price: u64,
+    pub max_supply: u64,
+    pub buy_fee_bps: u16,
+    pub sell_fee_bps: u16,
+    pub launch_fee: Option<LaunchFeeSchedule>,
+    pub launch_limits: Option<LaunchLimits>,
+    pub allowlist: Option<AllowlistPhase>,
+}
+
+/// Pricing formula a curve was created with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveKind {
+    /// price = base_price * (1 + supply / max_supply)
+    Linear,
+    /// price = base_price * 2^(supply / doubling_supply)
+    Exponential { doubling_supply: u64 },
+    /// price = base_price * (1 + supply / max_supply)^2
+    Quadratic,
+    /// pump.fun style x * y = k over virtual reserves. The parameters are
+    /// the initial reserves; the curve tracks the live ones in
+    /// `BondingCurve::virtual_sol_reserves` / `virtual_token_reserves`.
+    /// base_price is not used.
+    ConstantProduct {
+        virtual_sol_reserves: u64,
+        virtual_token_reserves: u64,
+    },
+    /// Segments of constant liquidity between sqrt prices, read from a
+    /// CurveConfig account. Same layout and math as Meteora DBC configs.
+    Piecewise { config: Pubkey },
+}
+
+impl CurveKind {
+    /// Check the parameters make sense for a curve capped at `max_supply`
+    pub fn validate(&self, max_supply: u64) -> Result<()> {
+        match *self {
+            CurveKind::Linear | CurveKind::Quadratic | CurveKind::Piecewise { .. } => {}
+            CurveKind::Exponential { doubling_supply } => {
+                require!(doubling_supply > 0, ErrorCode::InvalidCurveParams);
+                require!(
+                    max_supply / doubling_supply <= MAX_DOUBLINGS,
+                    ErrorCode::InvalidCurveParams
+                );
+            }
+            CurveKind::ConstantProduct {
+                virtual_sol_reserves,
+                virtual_token_reserves,
+            } => {
+                require!(virtual_sol_reserves > 0, ErrorCode::InvalidCurveParams);
+                require!(
+                    virtual_token_reserves > max_supply,
+                    ErrorCode::InvalidCurveParams
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a trade leaves the curve
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// Tokens minted by a buy or burned by a sell
+    pub tokens: u64,
+    /// Lamports entering the curve on a buy or leaving it on a sell, fees excluded
+    pub sol: u64,
+    /// Sqrt price after the trade (piecewise curves only, unchanged otherwise)
+    pub sqrt_price: u128,
+}
+
+impl BondingCurve {
+    /// Segments of a piecewise curve, checking the config account passed in
+    /// is the one the curve was created with. None for other kinds.
+    pub fn segments<'a>(
+        &self,
+        config: &'a Option<Account<'_, CurveConfig>>,
+    ) -> Result<Option<&'a CurveConfig>> {
+        match self.curve_kind {
+            CurveKind::Piecewise { config: expected } => {
+                let config = config.as_ref().ok_or(ErrorCode::CurveConfigMismatch)?;
+                require_keys_eq!(config.key(), expected, ErrorCode::CurveConfigMismatch);
+                Ok(Some(config))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Fill for `sol_amount` lamports entering the curve
+    pub fn fill_buy(&self, sol_amount: u64, config: Option<&CurveConfig>) -> Result<Fill> {
+        let mut sqrt_price = self.sqrt_price;
+        let tokens = match self.curve_kind {
+            CurveKind::Linear => polynomial::buy_tokens_out(
+                2,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::Quadratic => polynomial::buy_tokens_out(
+                3,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::Exponential { doubling_supply } => exponential::buy_tokens_out(
+                self.token_supply,
+                doubling_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::ConstantProduct { .. } => constant_product::buy_tokens_out(
+                self.virtual_sol_reserves,
+                self.virtual_token_reserves,
+                sol_amount,
+            )?,
+            CurveKind::Piecewise { .. } => {
+                let config = config.ok_or(ErrorCode::CurveConfigMismatch)?;
+                let (tokens, next) = piecewise::buy_tokens_out(config, self.sqrt_price, sol_amount)?;
+                sqrt_price = next;
+                tokens
+            }
+        };
+
+        Ok(Fill {
+            tokens,
+            sol: sol_amount,
+            sqrt_price,
+        })
+    }
+
+    /// Fill for minting exactly `token_amount` tokens, costing the
+    /// lamports rounded up
+    pub fn fill_buy_exact(&self, token_amount: u64, config: Option<&CurveConfig>) -> Result<Fill> {
+        let mut sqrt_price = self.sqrt_price;
+        let sol = match self.curve_kind {
+            CurveKind::Linear => polynomial::buy_sol_in(
+                2,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::Quadratic => polynomial::buy_sol_in(
+                3,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::Exponential { doubling_supply } => exponential::buy_sol_in(
+                self.token_supply,
+                doubling_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::ConstantProduct { .. } => constant_product::buy_sol_in(
+                self.virtual_sol_reserves,
+                self.virtual_token_reserves,
+                token_amount,
+            )?,
+            CurveKind::Piecewise { .. } => {
+                let config = config.ok_or(ErrorCode::CurveConfigMismatch)?;
+                let (sol, next) = piecewise::buy_sol_in(config, self.sqrt_price, token_amount)?;
+                sqrt_price = next;
+                sol
+            }
+        };
+
+        Ok(Fill {
+            tokens: token_amount,
+            sol,
+            sqrt_price,
+        })
+    }
+
+    /// Fill for burning `token_amount` tokens
+    pub fn fill_sell(&self, token_amount: u64, config: Option<&CurveConfig>) -> Result<Fill> {
+        require!(token_amount <= self.token_supply, ErrorCode::InsufficientSupply);
+
+        let mut sqrt_price = self.sqrt_price;
+        let sol = match self.curve_kind {
+            CurveKind::Linear => polynomial::sell_sol_out(
+                2,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::Quadratic => polynomial::sell_sol_out(
+                3,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::Exponential { doubling_supply } => exponential::sell_sol_out(
+                self.token_supply,
+                doubling_supply,
+                self.base_price,
+                token_amount,
+            )?,
+            CurveKind::ConstantProduct { .. } => constant_product::sell_sol_out(
+                self.virtual_sol_reserves,
+                self.virtual_token_reserves,
+                token_amount,
+            )?,
+            CurveKind::Piecewise { .. } => {
+                let config = config.ok_or(ErrorCode::CurveConfigMismatch)?;
+                let (sol, next) = piecewise::sell_sol_out(config, self.sqrt_price, token_amount)?;
+                sqrt_price = next;
+                sol
+            }
+        };
+
+        Ok(Fill {
+            tokens: token_amount,
+            sol,
+            sqrt_price,
+        })
+    }
+
+    /// Fill for paying out `sol_amount` lamports, burning the fewest tokens
+    /// that cover it
+    pub fn fill_sell_exact(&self, sol_amount: u64, config: Option<&CurveConfig>) -> Result<Fill> {
+        require!(sol_amount <= self.sol_reserves, ErrorCode::InsufficientReserves);
+
+        let mut sqrt_price = self.sqrt_price;
+        let tokens = match self.curve_kind {
+            CurveKind::Linear => polynomial::sell_tokens_in(
+                2,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::Quadratic => polynomial::sell_tokens_in(
+                3,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::Exponential { doubling_supply } => exponential::sell_tokens_in(
+                self.token_supply,
+                doubling_supply,
+                self.base_price,
+                sol_amount,
+            )?,
+            CurveKind::ConstantProduct { .. } => constant_product::sell_tokens_in(
+                self.virtual_sol_reserves,
+                self.virtual_token_reserves,
+                sol_amount,
+            )?,
+            CurveKind::Piecewise { .. } => {
+                let config = config.ok_or(ErrorCode::CurveConfigMismatch)?;
+                let (tokens, next) = piecewise::sell_tokens_in(config, self.sqrt_price, sol_amount)?;
+                sqrt_price = next;
+                tokens
+            }
+        };
+        require!(tokens <= self.token_supply, ErrorCode::InsufficientSupply);
+
+        Ok(Fill {
+            tokens,
+            sol: sol_amount,
+            sqrt_price,
+        })
+    }
+
+    /// Record a buy fill against the curve state
+    pub fn apply_buy(&mut self, fill: &Fill) -> Result<()> {
+        self.token_supply = self
+            .token_supply
+            .checked_add(fill.tokens)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sol_reserves = self
+            .sol_reserves
+            .checked_add(fill.sol)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sqrt_price = fill.sqrt_price;
+        self.move_virtual_reserves(fill, true)
+    }
+
+    /// Record a sell fill against the curve state
+    pub fn apply_sell(&mut self, fill: &Fill) -> Result<()> {
+        self.token_supply = self
+            .token_supply
+            .checked_sub(fill.tokens)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sol_reserves = self
+            .sol_reserves
+            .checked_sub(fill.sol)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.sqrt_price = fill.sqrt_price;
+        self.move_virtual_reserves(fill, false)
+    }
+
+    /// Set the virtual reserves of a constant product curve to their
+    /// initial values; other kinds keep them at zero
+    pub fn init_virtual_reserves(&mut self) {
+        if let CurveKind::ConstantProduct {
+            virtual_sol_reserves,
+            virtual_token_reserves,
+        } = self.curve_kind
+        {
+            self.virtual_sol_reserves = virtual_sol_reserves;
+            self.virtual_token_reserves = virtual_token_reserves;
+        }
+    }
+
+    /// Move the virtual reserves of a constant product curve by a fill,
+    /// SOL in and tokens out on a buy, the reverse on a sell
+    fn move_virtual_reserves(&mut self, fill: &Fill, buy: bool) -> Result<()> {
+        if !matches!(self.curve_kind, CurveKind::ConstantProduct { .. }) {
+            return Ok(());
+        }
+
+        let (sol, tokens) = if buy {
+            (
+                self.virtual_sol_reserves.checked_add(fill.sol),
+                self.virtual_token_reserves.checked_sub(fill.tokens),
+            )
+        } else {
+            (
+                self.virtual_sol_reserves.checked_sub(fill.sol),
+                self.virtual_token_reserves.checked_add(fill.tokens),
+            )
+        };
+        self.virtual_sol_reserves = sol.ok_or(ErrorCode::MathOverflow)?;
+        self.virtual_token_reserves = tokens.ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Spot price in lamports per whole token as Q64.64, rounded down
+    pub fn current_price(&self) -> Result<u128> {
+        match self.curve_kind {
+            CurveKind::Linear => polynomial::price(
+                2,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+            ),
+            CurveKind::Quadratic => polynomial::price(
+                3,
+                self.token_supply,
+                self.max_supply,
+                self.base_price,
+            ),
+            CurveKind::Exponential { doubling_supply } => {
+                exponential::price(self.token_supply, doubling_supply, self.base_price)
+            }
+            CurveKind::ConstantProduct { .. } => {
+                constant_product::price(self.virtual_sol_reserves, self.virtual_token_reserves)
+            }
+            CurveKind::Piecewise { .. } => piecewise::price(self.sqrt_price),
+        }
+    }
+}
+
+impl CurveConfig {
+    /// Check segments are well formed: non-empty, strictly increasing sqrt
+    /// prices inside the supported range, non-zero liquidity
+    pub fn validate(sqrt_start_price: u128, segments: &[CurveSegment]) -> Result<()> {
+        require!(
+            !segments.is_empty() && segments.len() <= MAX_CURVE_SEGMENTS,
+            ErrorCode::InvalidCurveParams
+        );
+        require!(
+            sqrt_start_price >= MIN_SQRT_PRICE,
+            ErrorCode::InvalidCurveParams
+        );
+
+        let mut lower = sqrt_start_price;
+        for segment in segments {
+            require!(segment.sqrt_price > lower, ErrorCode::InvalidCurveParams);
+            require!(segment.liquidity > 0, ErrorCode::InvalidCurveParams);
+            lower = segment.sqrt_price;
+        }
+        require!(lower <= MAX_SQRT_PRICE, ErrorCode::InvalidCurveParams);
+
+        Ok(())
+    }
+
+    /// Tokens the whole curve can sell, from the start price to the last
+    /// segment's upper sqrt price
+    pub fn token_capacity(&self) -> Result<u64> {
+        let mut lower = self.sqrt_start_price;
+        let mut total = U256::zero();
+        for segment in &self.segments {
+            total += piecewise::delta_base(lower, segment.sqrt_price, segment.liquidity, false);
+            lower = segment.sqrt_price;
+        }
+        to_u64(total.min(U256::from(u64::MAX)))
+    }
+}
+
+/// Power curves: price = base_price * ((M + s) / M)^(order - 1), M = max_supply.
+///
+/// Integrating over supply, moving from supply a to supply b costs
+///
+///     base_price * ((M + b)^order - (M + a)^order) / (order * TOKEN_PRECISION * M^(order - 1))
+///
+/// lamports. Linear curves have order 2, quadratic curves order 3. Both
+/// directions are evaluated exactly in 256-bit integers, buys inverting the
+/// integral with an integer root. Amounts paid out round down, amounts
+/// charged round up.
+mod polynomial {
+    use super::*;
+    use crate::math::{cube, icbrt, isqrt, mul_div_ceil, mul_div_floor, to_u64, U256, TOKEN_PRECISION};
+
+    fn pow(x: U256, order: u32) -> Result<U256> {
+        match order {
+            2 => x.checked_mul(x).ok_or_else(|| error!(ErrorCode::MathOverflow)),
+            _ => cube(x),
+        }
+    }
+
+    fn root(x: U256, order: u32) -> U256 {
+        match order {
+            2 => isqrt(x),
+            _ => icbrt(x),
+        }
+    }
+
+    /// order * TOKEN_PRECISION * max_supply^(order - 1)
+    fn denominator(order: u32, max_supply: u64) -> U256 {
+        let m = U256::from(max_supply);
+        let scale = U256::from(order) * U256::from(TOKEN_PRECISION);
+        match order {
+            2 => scale * m,
+            _ => scale * m * m,
+        }
+    }
+
+    /// sol_amount * denominator / base_price: the change in (M + s)^order
+    /// that `sol_amount` lamports pay for
+    fn budget(order: u32, max_supply: u64, base_price: u64, sol_amount: u64, round_up: bool) -> Result<U256> {
+        let scaled = U256::from(sol_amount)
+            .checked_mul(denominator(order, max_supply))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (quotient, remainder) = scaled.div_mod(U256::from(base_price));
+        Ok(if round_up && !remainder.is_zero() {
+            quotient + U256::one()
+        } else {
+            quotient
+        })
+    }
+
+    pub fn buy_tokens_out(
+        order: u32,
+        current_supply: u64,
+        max_supply: u64,
+        base_price: u64,
+        sol_amount: u64,
+    ) -> Result<u64> {
+        // Largest supply s' with cost(current_supply -> s') <= sol_amount:
+        // (M + s')^order <= (M + s)^order + sol_amount * denominator / base_price
+        let from = U256::from(max_supply) + U256::from(current_supply);
+        let target = pow(from, order)?
+            .checked_add(budget(order, max_supply, base_price, sol_amount, false)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        to_u64(root(target, order) - from)
+    }
+
+    pub fn sell_tokens_in(
+        order: u32,
+        current_supply: u64,
+        max_supply: u64,
+        base_price: u64,
+        sol_amount: u64,
+    ) -> Result<u64> {
+        // Largest supply s' with cost(s' -> current_supply) >= sol_amount, the
+        // same inversion as a buy run downwards:
+        // (M + s')^order <= (M + s)^order - ceil(sol_amount * denominator / base_price)
+        let from = U256::from(max_supply) + U256::from(current_supply);
+        let target = pow(from, order)?
+            .checked_sub(budget(order, max_supply, base_price, sol_amount, true)?)
+            .ok_or(ErrorCode::InsufficientReserves)?;
+        let to = root(target, order);
+        require!(to >= U256::from(max_supply), ErrorCode::InsufficientReserves);
+
+        to_u64(from - to)
+    }
+
+    pub fn buy_sol_in(
+        order: u32,
+        current_supply: u64,
+        max_supply: u64,
+        base_price: u64,
+        token_amount: u64,
+    ) -> Result<u64> {
+        let from = U256::from(max_supply) + U256::from(current_supply);
+        let to = from + U256::from(token_amount);
+        let area = pow(to, order)? - pow(from, order)?;
+
+        to_u64(mul_div_ceil(
+            area,
+            base_price,
+            denominator(order, max_supply),
+        )?)
+    }
+
+    pub fn sell_sol_out(
+        order: u32,
+        current_supply: u64,
+        max_supply: u64,
+        base_price: u64,
+        token_amount: u64,
+    ) -> Result<u64> {
+        let from = U256::from(max_supply) + U256::from(current_supply);
+        let to = from - U256::from(token_amount);
+        let area = pow(from, order)? - pow(to, order)?;
+
+        to_u64(mul_div_floor(
+            area,
+            base_price,
+            denominator(order, max_supply),
+        )?)
+    }
+
+    pub fn price(order: u32, supply: u64, max_supply: u64, base_price: u64) -> Result<u128> {
+        // base_price * ((M + s) / M)^(order - 1), exact up to the final floor
+        let m = U256::from(max_supply);
+        let s = m + U256::from(supply);
+        let (numerator, denominator) = match order {
+            2 => (s, m),
+            _ => (s * s, m * m),
+        };
+        to_u128(mul_div_floor(numerator << 64, base_price, denominator)?)
+    }
+}
+
+/// Exponential curves: price = base_price * 2^(s / H), H = doubling_supply.
+///
+/// Moving from supply a to supply b costs
+///
+///     base_price * H * (2^(b / H) - 2^(a / H)) / (TOKEN_PRECISION * ln 2)
+///
+/// lamports, with powers and logarithms taken in Q64.64. Buys invert the
+/// integral with log2 and then step back while the rounded cost still
+/// exceeds the budget, so the buyer never receives more than they paid for.
+mod exponential {
+    use super::*;
+    use crate::math::{
+        exp2_q64, log2_q64, mul_div_ceil, mul_div_floor, to_u64, U256, LN2_Q64, ONE_Q64,
+        TOKEN_PRECISION,
+    };
+
+    const MAX_ROUNDING_STEPS: usize = 4;
+
+    /// 2^(supply / doubling_supply) in Q64.64
+    fn growth(supply: u64, doubling_supply: u64) -> Result<u128> {
+        let exponent = (U256::from(supply) << 64) / U256::from(doubling_supply);
+        require!(exponent <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+        exp2_q64(exponent.as_u128())
+    }
+
+    /// TOKEN_PRECISION * ln 2, scaled by 2^64 like the growth terms it divides
+    fn denominator() -> U256 {
+        U256::from(TOKEN_PRECISION) * U256::from(LN2_Q64)
+    }
+
+    fn cost(from: u64, to: u64, doubling_supply: u64, base_price: u64) -> Result<u64> {
+        let area = growth(to, doubling_supply)? - growth(from, doubling_supply)?;
+        to_u64(mul_div_floor(
+            U256::from(area) * U256::from(doubling_supply),
+            base_price,
+            denominator(),
+        )?)
+    }
+
+    /// `cost` rounded up instead of down
+    fn cost_ceil(from: u64, to: u64, doubling_supply: u64, base_price: u64) -> Result<u64> {
+        let area = growth(to, doubling_supply)? - growth(from, doubling_supply)?;
+        to_u64(mul_div_ceil(
+            U256::from(area) * U256::from(doubling_supply),
+            base_price,
+            denominator(),
+        )?)
+    }
+
+    /// sol_amount * TOKEN_PRECISION * ln 2 / (base_price * H): the change in
+    /// growth that `sol_amount` lamports pay for
+    fn budget(sol_amount: u64, doubling_supply: u64, base_price: u64, round_up: bool) -> U256 {
+        let scaled = U256::from(sol_amount) * denominator();
+        let (quotient, remainder) =
+            scaled.div_mod(U256::from(base_price) * U256::from(doubling_supply));
+        if round_up && !remainder.is_zero() {
+            quotient + U256::one()
+        } else {
+            quotient
+        }
+    }
+
+    /// Supply at which growth reaches `target`, rounded down
+    fn supply_at(target: U256, doubling_supply: u64) -> Result<u64> {
+        require!(target <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+        let exponent = log2_q64(target.as_u128())?;
+        to_u64((U256::from(exponent) * U256::from(doubling_supply)) >> 64)
+    }
+
+    pub fn buy_tokens_out(
+        current_supply: u64,
+        doubling_supply: u64,
+        base_price: u64,
+        sol_amount: u64,
+    ) -> Result<u64> {
+        // 2^(s' / H) = 2^(s / H) + sol_amount * TOKEN_PRECISION * ln 2 / (base_price * H)
+        let target = U256::from(growth(current_supply, doubling_supply)?)
+            + budget(sol_amount, doubling_supply, base_price, false);
+        let new_supply = supply_at(target, doubling_supply)?;
+        let mut tokens = new_supply.saturating_sub(current_supply);
+
+        // exp2/log2 are off by a few ulps at most, far less than one token
+        // unit, so this steps back at most once or twice. The cost is rounded
+        // up so the buyer never gets tokens worth a fraction more than paid.
+        for _ in 0..MAX_ROUNDING_STEPS {
+            let end = current_supply
+                .checked_add(tokens)
+                .ok_or(ErrorCode::MathOverflow)?;
+            if tokens == 0 || cost_ceil(current_supply, end, doubling_supply, base_price)? <= sol_amount {
+                return Ok(tokens);
+            }
+            tokens -= 1;
+        }
+
+        err!(ErrorCode::MathOverflow)
+    }
+
+    pub fn buy_sol_in(
+        current_supply: u64,
+        doubling_supply: u64,
+        base_price: u64,
+        token_amount: u64,
+    ) -> Result<u64> {
+        let end = current_supply
+            .checked_add(token_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        cost_ceil(current_supply, end, doubling_supply, base_price)
+    }
+
+    pub fn sell_tokens_in(
+        current_supply: u64,
+        doubling_supply: u64,
+        base_price: u64,
+        sol_amount: u64,
+    ) -> Result<u64> {
+        // 2^(s' / H) = 2^(s / H) - sol_amount * TOKEN_PRECISION * ln 2 / (base_price * H)
+        let target = U256::from(growth(current_supply, doubling_supply)?)
+            .checked_sub(budget(sol_amount, doubling_supply, base_price, true))
+            .filter(|target| *target >= U256::from(ONE_Q64))
+            .ok_or(ErrorCode::InsufficientReserves)?;
+        let new_supply = supply_at(target, doubling_supply)?.min(current_supply);
+        let estimate = current_supply - new_supply;
+
+        // Same rounding slack as buys, stepping the other way
+        for tokens in (estimate..=current_supply).take(MAX_ROUNDING_STEPS) {
+            if cost(current_supply - tokens, current_supply, doubling_supply, base_price)? >= sol_amount {
+                return Ok(tokens);
+            }
+        }
+
+        // Selling the whole supply wasn't enough
+        require!(
+            current_supply - estimate >= MAX_ROUNDING_STEPS as u64,
+            ErrorCode::InsufficientReserves
+        );
+        err!(ErrorCode::MathOverflow)
+    }
+
+    pub fn sell_sol_out(
+        current_supply: u64,
+        doubling_supply: u64,
+        base_price: u64,
+        token_amount: u64,
+    ) -> Result<u64> {
+        cost(
+            current_supply - token_amount,
+            current_supply,
+            doubling_supply,
+            base_price,
+        )
+    }
+
+    pub fn price(supply: u64, doubling_supply: u64, base_price: u64) -> Result<u128> {
+        // growth is already Q64.64
+        to_u128(U256::from(growth(supply, doubling_supply)?) * U256::from(base_price))
+    }
+}
+
+/// Constant product over virtual reserves: x * y = k, with
+/// x = virtual SOL reserves and y = virtual token reserves. Outputs are
+/// rounded down, inputs up.
+mod constant_product {
+    use super::*;
+    use crate::math::{to_u64, U256, TOKEN_PRECISION};
+
+    pub fn buy_tokens_out(sol_reserves: u64, token_reserves: u64, sol_amount: u64) -> Result<u64> {
+        // dy = y * dx / (x + dx)
+        to_u64(
+            U256::from(token_reserves) * U256::from(sol_amount)
+                / (U256::from(sol_reserves) + U256::from(sol_amount)),
+        )
+    }
+
+    pub fn buy_sol_in(sol_reserves: u64, token_reserves: u64, token_amount: u64) -> Result<u64> {
+        // dx = x * dy / (y - dy), rounded up
+        require!(token_amount < token_reserves, ErrorCode::MaxSupplyExceeded);
+        let numerator = U256::from(sol_reserves) * U256::from(token_amount);
+        let denominator = U256::from(token_reserves - token_amount);
+        let (quotient, remainder) = numerator.div_mod(denominator);
+        to_u64(if remainder.is_zero() { quotient } else { quotient + U256::one() })
+    }
+
+    pub fn sell_sol_out(sol_reserves: u64, token_reserves: u64, token_amount: u64) -> Result<u64> {
+        // dx = x * dy / (y + dy)
+        to_u64(
+            U256::from(sol_reserves) * U256::from(token_amount)
+                / (U256::from(token_reserves) + U256::from(token_amount)),
+        )
+    }
+
+    pub fn sell_tokens_in(sol_reserves: u64, token_reserves: u64, sol_amount: u64) -> Result<u64> {
+        // dy = y * dx / (x - dx), rounded up
+        require!(sol_amount < sol_reserves, ErrorCode::InsufficientReserves);
+        let numerator = U256::from(token_reserves) * U256::from(sol_amount);
+        let denominator = U256::from(sol_reserves - sol_amount);
+        let (quotient, remainder) = numerator.div_mod(denominator);
+        to_u64(if remainder.is_zero() { quotient } else { quotient + U256::one() })
+    }
+
+    pub fn price(sol_reserves: u64, token_reserves: u64) -> Result<u128> {
+        require!(token_reserves > 0, ErrorCode::MathOverflow);
+        let numerator = (U256::from(sol_reserves) * U256::from(TOKEN_PRECISION)) << 64;
+        to_u128(numerator / U256::from(token_reserves))
+    }
+}
+
+/// Piecewise curves in Meteora DBC layout. Each segment runs from the
+/// previous segment's sqrt price (or the config's start price) up to its own
+/// sqrt price with constant liquidity L. Sqrt prices are Q64.64 of
+/// sqrt(lamports per token base unit), and within a segment
+///
+///     tokens = L * (upper - lower) / (lower * upper)
+///     sol    = L * (upper - lower) / 2^128
+///
+/// Trades walk segments from the curve's current sqrt price, rounding every
+/// step in the curve's favour like DBC does.
+mod piecewise {
+    use super::*;
+    use crate::math::TOKEN_PRECISION;
+
+    /// Tokens between two sqrt prices
+    pub fn delta_base(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> U256 {
+        let numerator = U256::from(liquidity) * U256::from(upper - lower);
+        let denominator = U256::from(lower) * U256::from(upper);
+        div(numerator, denominator, round_up)
+    }
+
+    /// Lamports between two sqrt prices
+    fn delta_quote(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> U256 {
+        let product = U256::from(liquidity) * U256::from(upper - lower);
+        div(product, U256::one() << 128, round_up)
+    }
+
+    fn div(numerator: U256, denominator: U256, round_up: bool) -> U256 {
+        let (quotient, remainder) = numerator.div_mod(denominator);
+        if round_up && !remainder.is_zero() {
+            quotient + U256::one()
+        } else {
+            quotient
+        }
+    }
+
+    /// Sqrt price after `sol` lamports enter a segment, rounded down
+    fn next_sqrt_price_from_sol(sqrt_price: u128, liquidity: u128, sol: u64) -> Result<u128> {
+        let step = (U256::from(sol) << 128) / U256::from(liquidity);
+        let next = U256::from(sqrt_price) + step;
+        require!(next <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+        Ok(next.as_u128())
+    }
+
+    /// Sqrt price after `tokens` enter a segment, rounded up
+    fn next_sqrt_price_from_tokens(sqrt_price: u128, liquidity: u128, tokens: u64) -> u128 {
+        let numerator = U256::from(liquidity) * U256::from(sqrt_price);
+        let denominator = U256::from(liquidity) + U256::from(tokens) * U256::from(sqrt_price);
+        div(numerator, denominator, true).as_u128()
+    }
+
+    /// Sqrt price after `tokens` leave a segment, rounded up
+    fn next_sqrt_price_from_tokens_out(sqrt_price: u128, liquidity: u128, tokens: u64) -> Result<u128> {
+        let taken = U256::from(tokens) * U256::from(sqrt_price);
+        require!(taken < U256::from(liquidity), ErrorCode::MathOverflow);
+        let numerator = U256::from(liquidity) * U256::from(sqrt_price);
+        let next = div(numerator, U256::from(liquidity) - taken, true);
+        require!(next <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+        Ok(next.as_u128())
+    }
+
+    /// Sqrt price after `sol` lamports leave a segment, rounded down
+    fn next_sqrt_price_from_sol_out(sqrt_price: u128, liquidity: u128, sol: u64) -> Result<u128> {
+        let step = div(U256::from(sol) << 128, U256::from(liquidity), true);
+        require!(step <= U256::from(sqrt_price), ErrorCode::InsufficientReserves);
+        Ok((U256::from(sqrt_price) - step).as_u128())
+    }
+
+    /// Segments as (lower, upper, liquidity)
+    fn ranges(config: &CurveConfig) -> impl DoubleEndedIterator<Item = (u128, u128, u128)> + '_ {
+        (0..config.segments.len()).map(move |i| {
+            let lower = match i {
+                0 => config.sqrt_start_price,
+                _ => config.segments[i - 1].sqrt_price,
+            };
+            let segment = &config.segments[i];
+            (lower, segment.sqrt_price, segment.liquidity)
+        })
+    }
+
+    pub fn buy_tokens_out(config: &CurveConfig, sqrt_price: u128, sol_amount: u64) -> Result<(u64, u128)> {
+        let mut sqrt_price = sqrt_price;
+        let mut remaining = sol_amount;
+        let mut tokens = U256::zero();
+
+        for (_, upper, liquidity) in ranges(config) {
+            if remaining == 0 {
+                break;
+            }
+            if upper <= sqrt_price {
+                continue;
+            }
+
+            let segment_sol = delta_quote(sqrt_price, upper, liquidity, true);
+            if U256::from(remaining) < segment_sol {
+                let next = next_sqrt_price_from_sol(sqrt_price, liquidity, remaining)?;
+                tokens += delta_base(sqrt_price, next, liquidity, false);
+                sqrt_price = next;
+                remaining = 0;
+            } else {
+                tokens += delta_base(sqrt_price, upper, liquidity, false);
+                remaining -= segment_sol.as_u64();
+                sqrt_price = upper;
+            }
+        }
+
+        // Running off the last segment leaves `remaining` unfilled; buys
+        // reject that through max_supply, which is below the curve capacity.
+        Ok((to_u64(tokens)?, sqrt_price))
+    }
+
+    pub fn buy_sol_in(config: &CurveConfig, sqrt_price: u128, token_amount: u64) -> Result<(u64, u128)> {
+        let mut sqrt_price = sqrt_price;
+        let mut remaining = token_amount;
+        let mut sol = U256::zero();
+
+        for (_, upper, liquidity) in ranges(config) {
+            if remaining == 0 {
+                break;
+            }
+            if upper <= sqrt_price {
+                continue;
+            }
+
+            let segment_tokens = delta_base(sqrt_price, upper, liquidity, false);
+            if U256::from(remaining) < segment_tokens {
+                let next = next_sqrt_price_from_tokens_out(sqrt_price, liquidity, remaining)?;
+                sol += delta_quote(sqrt_price, next, liquidity, true);
+                sqrt_price = next;
+                remaining = 0;
+            } else {
+                sol += delta_quote(sqrt_price, upper, liquidity, true);
+                remaining -= segment_tokens.as_u64();
+                sqrt_price = upper;
+            }
+        }
+
+        require!(remaining == 0, ErrorCode::MaxSupplyExceeded);
+        Ok((to_u64(sol)?, sqrt_price))
+    }
+
+    pub fn sell_sol_out(config: &CurveConfig, sqrt_price: u128, token_amount: u64) -> Result<(u64, u128)> {
+        let mut sqrt_price = sqrt_price;
+        let mut remaining = token_amount;
+        let mut sol = U256::zero();
+
+        for (lower, _, liquidity) in ranges(config).rev() {
+            if remaining == 0 {
+                break;
+            }
+            if lower >= sqrt_price {
+                continue;
+            }
+
+            let segment_tokens = delta_base(lower, sqrt_price, liquidity, true);
+            if U256::from(remaining) < segment_tokens {
+                let next = next_sqrt_price_from_tokens(sqrt_price, liquidity, remaining);
+                sol += delta_quote(next, sqrt_price, liquidity, false);
+                sqrt_price = next;
+                remaining = 0;
+            } else {
+                sol += delta_quote(lower, sqrt_price, liquidity, false);
+                remaining -= segment_tokens.as_u64();
+                sqrt_price = lower;
+            }
+        }
+
+        // Tokens left over at the start price are worth nothing and burn for free
+        Ok((to_u64(sol)?, sqrt_price))
+    }
+
+    pub fn sell_tokens_in(config: &CurveConfig, sqrt_price: u128, sol_amount: u64) -> Result<(u64, u128)> {
+        let mut sqrt_price = sqrt_price;
+        let mut remaining = sol_amount;
+        let mut tokens = U256::zero();
+
+        for (lower, _, liquidity) in ranges(config).rev() {
+            if remaining == 0 {
+                break;
+            }
+            if lower >= sqrt_price {
+                continue;
+            }
+
+            let segment_sol = delta_quote(lower, sqrt_price, liquidity, false);
+            if U256::from(remaining) < segment_sol {
+                let next = next_sqrt_price_from_sol_out(sqrt_price, liquidity, remaining)?;
+                tokens += delta_base(next, sqrt_price, liquidity, true);
+                sqrt_price = next;
+                remaining = 0;
+            } else {
+                tokens += delta_base(lower, sqrt_price, liquidity, true);
+                remaining -= segment_sol.as_u64();
+                sqrt_price = lower;
+            }
+        }
+
+        require!(remaining == 0, ErrorCode::InsufficientReserves);
+        Ok((to_u64(tokens)?, sqrt_price))
+    }
+
+    pub fn price(sqrt_price: u128) -> Result<u128> {
+        // Q128 per base unit squared, back to Q64.64 per whole token
+        let squared = U256::from(sqrt_price) * U256::from(sqrt_price);
+        to_u128((squared * U256::from(TOKEN_PRECISION)) >> 64)
+    }
+}
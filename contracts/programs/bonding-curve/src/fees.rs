@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// Highest `max_fee_bps` the platform config accepts (10%)
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Fee of `fee_bps` taken out of a gross SOL amount, rounded up in the
+/// protocol's favour
+pub fn fee_on(gross: u64, fee_bps: u16) -> Result<u64> {
+    let fee = ((gross as u128) * (fee_bps as u128)).div_ceil(BPS_DENOMINATOR as u128);
+    Ok(fee as u64)
+}
+
+/// Smallest gross amount that still leaves `net` after `fee_on`.
+///
+/// With the fee rounded up, gross - fee_on(gross) = floor(gross * (1 - fee)),
+/// so the answer is ceil(net / (1 - fee)).
+pub fn gross_for_net(net: u64, fee_bps: u16) -> Result<u64> {
+    let gross = ((net as u128) * (BPS_DENOMINATOR as u128))
+        .div_ceil((BPS_DENOMINATOR - fee_bps as u64) as u128);
+    u64::try_from(gross).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// A trade fee divided between its recipients
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeSplit {
+    pub platform: u64,
+    pub creator: u64,
+    pub referrer: u64,
+}
+
+impl FeeSplit {
+    /// Give the creator and referrer their shares of `fee`, in basis points
+    /// of the fee and rounded down, and the platform the rest. Pass a zero
+    /// referrer share when the trade has no referrer.
+    pub fn new(fee: u64, creator_share_bps: u16, referrer_share_bps: u16) -> Result<Self> {
+        let creator = share_of(fee, creator_share_bps);
+        let referrer = share_of(fee, referrer_share_bps);
+        let platform = fee
+            .checked_sub(creator)
+            .and_then(|rest| rest.checked_sub(referrer))
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(Self { platform, creator, referrer })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.platform + self.creator + self.referrer
+    }
+}
+
+fn share_of(amount: u64, share_bps: u16) -> u64 {
+    ((amount as u128) * (share_bps as u128) / BPS_DENOMINATOR as u128) as u64
+}
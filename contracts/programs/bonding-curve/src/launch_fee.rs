@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+
+use crate::fees::FeeSplit;
+use crate::{BondingCurve, ErrorCode};
+
+/// Highest buy fee a launch schedule may start at (50%)
+pub const MAX_LAUNCH_FEE_BPS: u16 = 5_000;
+
+/// Halvings an exponential schedule goes through over its duration
+pub const EXPONENTIAL_HALF_LIVES: u64 = 8;
+
+/// Anti-sniper surcharge on the first buys after launch. The buy fee starts
+/// at `start_fee_bps` and decays to the curve's `buy_fee_bps` over
+/// `duration_slots` slots from `launch_slot`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct LaunchFeeSchedule {
+    pub start_fee_bps: u16,
+    pub duration_slots: u64,
+    pub decay: FeeDecay,
+    /// Who gets the part of the fee above the normal buy fee
+    pub recipient: LaunchFeeRecipient,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum FeeDecay {
+    /// Straight line down to the buy fee
+    Linear,
+    /// Surcharge halves `EXPONENTIAL_HALF_LIVES` times over the duration,
+    /// interpolated between halvings and reaching zero at the end
+    Exponential,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum LaunchFeeRecipient {
+    Creator,
+    Treasury,
+}
+
+impl LaunchFeeSchedule {
+    pub fn validate(&self, buy_fee_bps: u16) -> Result<()> {
+        require!(
+            self.start_fee_bps > buy_fee_bps && self.start_fee_bps <= MAX_LAUNCH_FEE_BPS,
+            ErrorCode::InvalidLaunchFee
+        );
+        require!(self.duration_slots > 0, ErrorCode::InvalidLaunchFee);
+        Ok(())
+    }
+
+    /// Surcharge over `buy_fee_bps` left `elapsed` slots after launch,
+    /// rounded down
+    pub fn surcharge_bps(&self, buy_fee_bps: u16, elapsed: u64) -> u16 {
+        if elapsed >= self.duration_slots {
+            return 0;
+        }
+        let excess = self.start_fee_bps.saturating_sub(buy_fee_bps) as u128;
+        let duration = self.duration_slots as u128;
+        let elapsed = elapsed as u128;
+
+        let surcharge = match self.decay {
+            FeeDecay::Linear => excess * (duration - elapsed) / duration,
+            FeeDecay::Exponential => {
+                // Position in half-lives, then a straight line between the
+                // halvings either side of it
+                let position = elapsed * EXPONENTIAL_HALF_LIVES as u128;
+                let halvings = (position / duration) as u32;
+                let through = position % duration;
+                let high = excess >> halvings;
+                let low = if halvings as u64 + 1 == EXPONENTIAL_HALF_LIVES {
+                    0
+                } else {
+                    excess >> (halvings + 1)
+                };
+                high - (high - low) * through / duration
+            }
+        };
+        surcharge as u16
+    }
+}
+
+impl BondingCurve {
+    /// Buy fee rate at `slot`: the curve's buy fee plus whatever is left of
+    /// its launch surcharge
+    pub fn buy_fee_bps_at(&self, slot: u64) -> u16 {
+        match &self.launch_fee {
+            Some(schedule) => {
+                let elapsed = slot.saturating_sub(self.launch_slot);
+                self.buy_fee_bps + schedule.surcharge_bps(self.buy_fee_bps, elapsed)
+            }
+            None => self.buy_fee_bps,
+        }
+    }
+
+    /// Part of a buy `fee` charged at `fee_bps` that is launch surcharge.
+    /// Rounds down, so the normal fee keeps the rounding.
+    pub fn launch_fee_of(&self, fee: u64, fee_bps: u16) -> u64 {
+        if fee_bps <= self.buy_fee_bps {
+            return 0;
+        }
+        let surcharge_bps = (fee_bps - self.buy_fee_bps) as u128;
+        ((fee as u128) * surcharge_bps / fee_bps as u128) as u64
+    }
+
+    /// Add a launch surcharge to the leg of its recipient; the treasury
+    /// receives the platform leg
+    pub fn route_launch_fee(&self, split: &mut FeeSplit, launch_fee: u64) -> Result<()> {
+        let leg = match self.launch_fee.map(|schedule| schedule.recipient) {
+            Some(LaunchFeeRecipient::Creator) => &mut split.creator,
+            _ => &mut split.platform,
+        };
+        *leg = leg.checked_add(launch_fee).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+use crate::fees::BPS_DENOMINATOR;
+use crate::{BondingCurve, ErrorCode, WalletPurchases};
+
+/// Buy caps for the first `duration_secs` seconds after a curve's
+/// `created_at`, so bots can't take the early supply
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct LaunchLimits {
+    pub duration_secs: u32,
+    /// Most tokens a single buy may mint
+    pub max_tokens_per_buy: u64,
+    /// Most tokens one wallet may buy in total, in basis points of
+    /// `max_supply`
+    pub max_wallet_bps: u16,
+}
+
+impl LaunchLimits {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.duration_secs > 0, ErrorCode::InvalidLaunchLimits);
+        require!(self.max_tokens_per_buy > 0, ErrorCode::InvalidLaunchLimits);
+        require!(
+            self.max_wallet_bps > 0 && self.max_wallet_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidLaunchLimits
+        );
+        Ok(())
+    }
+}
+
+impl BondingCurve {
+    /// The launch limits, while they still apply at unix time `now`
+    pub fn active_launch_limits(&self, now: i64) -> Option<&LaunchLimits> {
+        self.launch_limits
+            .as_ref()
+            .filter(|limits| now < self.created_at.saturating_add(limits.duration_secs as i64))
+    }
+
+    /// Most tokens one wallet may buy under `limits`, rounded down
+    pub fn max_wallet_tokens(&self, limits: &LaunchLimits) -> u64 {
+        ((self.max_supply as u128) * (limits.max_wallet_bps as u128) / BPS_DENOMINATOR as u128) as u64
+    }
+
+    /// Check a buy of `tokens` made at `now` against the launch limits and
+    /// the buyer's allowlist `allocation`, and add it to the wallet's total.
+    /// With neither in force there is nothing to check and `purchases` may
+    /// be absent.
+    pub fn check_launch_limits(
+        &self,
+        now: i64,
+        tokens: u64,
+        allocation: Option<u64>,
+        purchases: Option<&mut WalletPurchases>,
+    ) -> Result<()> {
+        let limits = self.active_launch_limits(now);
+        if limits.is_none() && allocation.is_none() {
+            return Ok(());
+        }
+        if let Some(limits) = limits {
+            require!(tokens <= limits.max_tokens_per_buy, ErrorCode::LaunchBuyTooLarge);
+        }
+
+        let purchases = purchases.ok_or(ErrorCode::WalletPurchasesRequired)?;
+        let total = purchases.tokens_bought.checked_add(tokens).ok_or(ErrorCode::MathOverflow)?;
+        if let Some(limits) = limits {
+            require!(total <= self.max_wallet_tokens(limits), ErrorCode::LaunchWalletCapExceeded);
+        }
+        if let Some(allocation) = allocation {
+            require!(total <= allocation, ErrorCode::AllowlistAllocationExceeded);
+        }
+        purchases.tokens_bought = total;
+        Ok(())
+    }
+}
@@ -1,5 +1,28 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn, Transfer};
+use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn};
+
+pub mod allowlist;
+pub mod config;
+pub mod curve;
+pub mod fees;
+pub mod launch_fee;
+pub mod launch_limits;
+pub mod math;
+pub mod quote;
+pub mod referral;
+pub mod token_factory;
+pub mod treasury;
+pub mod vault;
+
+use allowlist::{AllowlistPhase, AllowlistProof};
+use config::{ConfigParams, ConfigUpdate};
+use curve::{CurveKind, CurveParams, Fill, MAX_CURVE_SEGMENTS};
+use fees::FeeSplit;
+use launch_fee::LaunchFeeSchedule;
+use launch_limits::LaunchLimits;
+use quote::TradeQuote;
+use referral::MAX_REFERRAL_CODE_LEN;
+use treasury::MAX_REVENUE_RECIPIENTS;
 
 declare_id!("2bkDb7cox1a36tSuGdkTJAmmb4Qmm9yudSTbpL5yqmuz");
 
@@ -7,185 +30,559 @@ declare_id!("2bkDb7cox1a36tSuGdkTJAmmb4Qmm9yudSTbpL5yqmuz");
 pub mod bonding_curve {
     use super::*;
 
-    /// Initialize a new bonding curve for a token
+    /// Create the singleton platform config and the treasury that collects
+    /// platform fees; the signer, who must be the program's upgrade
+    /// authority, becomes the admin
+    pub fn initialize_config(ctx: Context<InitializeConfig>, params: ConfigParams) -> Result<()> {
+        let ConfigParams {
+            max_fee_bps,
+            creator_fee_share_bps,
+            referrer_fee_share_bps,
+            post_graduation_fee_bps,
+            graduation_threshold,
+            min_base_price,
+            max_base_price,
+        } = params;
+        let config = &mut ctx.accounts.platform_config;
+        
+        config.admin = ctx.accounts.admin.key();
+        config.fee_collector = ctx.accounts.treasury.key();
+        config.graduation_authority = config::graduation_authority();
+        config.max_fee_bps = max_fee_bps;
+        config.creator_fee_share_bps = creator_fee_share_bps;
+        config.referrer_fee_share_bps = referrer_fee_share_bps;
+        config.post_graduation_fee_bps = post_graduation_fee_bps;
+        config.graduation_threshold = graduation_threshold;
+        config.min_base_price = min_base_price;
+        config.max_base_price = max_base_price;
+        config.paused = false;
+        config.bump = ctx.bumps.platform_config;
+        config.validate()?;
+        
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.recipients = Vec::new();
+        treasury.total_distributed = 0;
+        treasury.bump = ctx.bumps.treasury;
+        
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            fee_collector: config.fee_collector,
+            max_fee_bps,
+            creator_fee_share_bps,
+            referrer_fee_share_bps,
+            post_graduation_fee_bps,
+            graduation_threshold,
+            min_base_price,
+            max_base_price,
+            paused: false,
+        });
+        
+        Ok(())
+    }
+
+    /// Change platform parameters (admin only)
+    pub fn update_config(ctx: Context<UpdateConfig>, update: ConfigUpdate) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+        
+        config.apply(&update)?;
+        
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            fee_collector: config.fee_collector,
+            max_fee_bps: config.max_fee_bps,
+            creator_fee_share_bps: config.creator_fee_share_bps,
+            referrer_fee_share_bps: config.referrer_fee_share_bps,
+            post_graduation_fee_bps: config.post_graduation_fee_bps,
+            graduation_threshold: config.graduation_threshold,
+            min_base_price: config.min_base_price,
+            max_base_price: config.max_base_price,
+            paused: config.paused,
+        });
+        
+        Ok(())
+    }
+
+    /// Initialize a new bonding curve for a token, with its own buy and sell
+    /// fees up to the platform's `max_fee_bps`, an optional anti-sniper
+    /// launch fee on early buys, optional caps on early buy sizes and an
+    /// optional allowlist presale. Charges the token factory's creation fee
+    /// into the treasury unless the creator is a factory partner.
     pub fn initialize_curve(
         ctx: Context<InitializeCurve>,
-        base_price: u64,
-        max_supply: u64,
+        curve_kind: CurveKind,
+        params: CurveParams,
     ) -> Result<()> {
-        let curve = &mut ctx.accounts.bonding_curve;
+        let CurveParams {
+            base_price,
+            max_supply,
+            buy_fee_bps,
+            sell_fee_bps,
+            launch_fee,
+            launch_limits,
+            allowlist,
+        } = params;
+        let platform = &ctx.accounts.platform_config;
         
-        require!(base_price > 0, ErrorCode::InvalidBasePrice);
+        platform.require_active()?;
+        require!(
+            base_price >= platform.min_base_price && base_price <= platform.max_base_price,
+            ErrorCode::InvalidBasePrice
+        );
         require!(max_supply > 0, ErrorCode::InvalidMaxSupply);
+        require!(
+            buy_fee_bps <= platform.max_fee_bps && sell_fee_bps <= platform.max_fee_bps,
+            ErrorCode::FeeTooHigh
+        );
+        curve_kind.validate(max_supply)?;
+        if let Some(schedule) = &launch_fee {
+            schedule.validate(buy_fee_bps)?;
+        }
+        if let Some(limits) = &launch_limits {
+            limits.validate()?;
+        }
+        
+        let clock = Clock::get()?;
+        if let Some(phase) = &allowlist {
+            phase.validate(clock.unix_timestamp)?;
+        }
         
+        let creation_fee = ctx.accounts.charge_creation_fee()?;
+        
+        let curve = &mut ctx.accounts.bonding_curve;
         curve.token_mint = ctx.accounts.token_mint.key();
         curve.creator = ctx.accounts.creator.key();
         curve.base_price = base_price;
         curve.token_supply = 0;
         curve.max_supply = max_supply;
         curve.sol_reserves = 0;
-        curve.fee_collector = ctx.accounts.fee_collector.key();
+        curve.fee_collector = platform.fee_collector;
         curve.graduated = false;
-        curve.created_at = Clock::get()?.unix_timestamp;
+        curve.complete = false;
+        curve.created_at = clock.unix_timestamp;
         curve.bump = ctx.bumps.bonding_curve;
+        curve.curve_kind = curve_kind;
+        curve.init_virtual_reserves();
+        curve.buy_fee_bps = buy_fee_bps;
+        curve.sell_fee_bps = sell_fee_bps;
+        curve.post_graduation_fee_bps = platform.post_graduation_fee_bps;
+        curve.launch_slot = clock.slot;
+        curve.launch_fee = launch_fee;
+        curve.launch_limits = launch_limits;
+        curve.allowlist = allowlist;
+        
+        if let Some(config) = curve.segments(&ctx.accounts.curve_config)? {
+            // Buys that run past the last segment must trip MaxSupplyExceeded
+            require!(config.token_capacity()? > max_supply, ErrorCode::InvalidCurveParams);
+            curve.sqrt_price = config.sqrt_start_price;
+        }
+        
+        ctx.accounts.sol_vault.bump = ctx.bumps.sol_vault;
+        ctx.accounts.creator_fee_vault.bump = ctx.bumps.creator_fee_vault;
         
         emit!(CurveInitialized {
             token_mint: curve.token_mint,
             creator: curve.creator,
             base_price,
             max_supply,
+            curve_kind,
+            buy_fee_bps,
+            sell_fee_bps,
+            post_graduation_fee_bps: curve.post_graduation_fee_bps,
+            launch_fee,
+            launch_limits,
+            allowlist,
+            creation_fee,
         });
         
         Ok(())
     }
 
-    /// Buy tokens with SOL
-    pub fn buy(ctx: Context<Buy>, sol_amount: u64, min_tokens_out: u64) -> Result<()> {
-        let curve = &mut ctx.accounts.bonding_curve;
+    /// Open the account tracking a wallet's purchases from a curve, which
+    /// `buy` needs while the curve's launch limits or the wallet's allowlist
+    /// allocation apply
+    pub fn initialize_wallet_purchases(ctx: Context<InitializeWalletPurchases>) -> Result<()> {
+        let purchases = &mut ctx.accounts.wallet_purchases;
+        purchases.tokens_bought = 0;
+        purchases.bump = ctx.bumps.wallet_purchases;
         
-        require!(!curve.graduated, ErrorCode::CurveGraduated);
-        require!(sol_amount > 0, ErrorCode::InvalidAmount);
+        Ok(())
+    }
+
+    /// Create a piecewise curve config from (sqrt price, liquidity) segments,
+    /// e.g. exported from a Meteora DBC config
+    pub fn create_curve_config(
+        ctx: Context<CreateCurveConfig>,
+        sqrt_start_price: u128,
+        segments: Vec<CurveSegment>,
+    ) -> Result<()> {
+        CurveConfig::validate(sqrt_start_price, &segments)?;
+        
+        let config = &mut ctx.accounts.curve_config;
+        config.authority = ctx.accounts.authority.key();
+        config.sqrt_start_price = sqrt_start_price;
+        config.segments = segments;
+        
+        emit!(CurveConfigCreated {
+            curve_config: config.key(),
+            authority: config.authority,
+            sqrt_start_price,
+            segment_count: config.segments.len() as u8,
+        });
+        
+        Ok(())
+    }
+
+    /// Buy tokens with SOL. With `allow_partial`, a buy past max_supply
+    /// fills up to max_supply and only charges for that part. During an
+    /// allowlist phase the buyer must prove its place on the allowlist.
+    pub fn buy(
+        ctx: Context<Buy>,
+        sol_amount: u64,
+        min_tokens_out: u64,
+        allow_partial: bool,
+        allowlist_proof: Option<AllowlistProof>,
+    ) -> Result<()> {
+        let curve = &ctx.accounts.bonding_curve;
+        let platform = &ctx.accounts.platform_config;
         
-        // Calculate fee (1%)
-        let fee = sol_amount.checked_div(100).ok_or(ErrorCode::MathOverflow)?;
-        let sol_after_fee = sol_amount.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        platform.require_active()?;
         
-        // Calculate tokens to mint based on bonding curve formula
-        let tokens_to_mint = calculate_buy_tokens(
-            curve.token_supply,
-            curve.max_supply,
-            curve.base_price,
-            sol_after_fee,
+        // Calculate fee and tokens to mint based on bonding curve formula
+        let clock = Clock::get()?;
+        let fee_bps = curve.buy_fee_bps_at(clock.slot);
+        let (fill, quote) = curve.quote_buy(
+            sol_amount,
+            allow_partial,
+            curve.segments(&ctx.accounts.curve_config)?,
+            platform,
+            clock.slot,
         )?;
+        let fee = quote.fee;
+        let tokens_to_mint = fill.tokens;
         
         require!(tokens_to_mint >= min_tokens_out, ErrorCode::SlippageExceeded);
-        require!(
-            curve.token_supply.checked_add(tokens_to_mint).ok_or(ErrorCode::MathOverflow)? <= curve.max_supply,
-            ErrorCode::MaxSupplyExceeded
-        );
+        let allocation = curve.check_allowlist(
+            clock.unix_timestamp,
+            &ctx.accounts.buyer.key(),
+            allowlist_proof.as_ref(),
+        )?;
+        curve.check_launch_limits(
+            clock.unix_timestamp,
+            tokens_to_mint,
+            allocation,
+            ctx.accounts.wallet_purchases.as_deref_mut(),
+        )?;
         
-        // Transfer SOL from buyer to curve
-        let transfer_ctx = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.buyer.to_account_info(),
-                to: ctx.accounts.sol_vault.to_account_info(),
-            },
-        );
-        anchor_lang::system_program::transfer(transfer_ctx, sol_after_fee)?;
+        let launch_fee = curve.launch_fee_of(fee, fee_bps);
+        let split = ctx.accounts.split_fee(fee, launch_fee)?;
+        ctx.accounts.settle(&fill, &split)?;
         
-        // Transfer fee to fee collector
-        let fee_transfer_ctx = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.buyer.to_account_info(),
-                to: ctx.accounts.fee_collector.to_account_info(),
-            },
+        emit!(TokensBought {
+            buyer: ctx.accounts.buyer.key(),
+            token_mint: ctx.accounts.bonding_curve.token_mint,
+            sol_amount: sol_amount - quote.sol_refunded,
+            tokens_received: tokens_to_mint,
+            fee,
+            fee_bps,
+            launch_fee,
+            platform_fee: split.platform,
+            creator_fee: split.creator,
+            referrer_fee: split.referrer,
+            referrer: ctx.accounts.referral.as_ref().map(|referral| referral.owner),
+            sol_refunded: quote.sol_refunded,
+            price: ctx.accounts.bonding_curve.current_price()?,
+        });
+        
+        ctx.accounts.complete_at_threshold()?;
+        
+        Ok(())
+    }
+
+    /// Buy exactly `token_amount` tokens, paying at most `max_sol_in`
+    /// lamports including the fee
+    pub fn buy_exact_out(
+        ctx: Context<Buy>,
+        token_amount: u64,
+        max_sol_in: u64,
+        allowlist_proof: Option<AllowlistProof>,
+    ) -> Result<()> {
+        let curve = &ctx.accounts.bonding_curve;
+        let platform = &ctx.accounts.platform_config;
+        
+        platform.require_active()?;
+        curve.require_trading()?;
+        require!(token_amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            curve.token_supply.checked_add(token_amount).ok_or(ErrorCode::MathOverflow)? <= curve.max_supply,
+            ErrorCode::MaxSupplyExceeded
         );
-        anchor_lang::system_program::transfer(fee_transfer_ctx, fee)?;
         
-        // Mint tokens to buyer
-        let seeds = &[
-            b"bonding_curve",
-            curve.token_mint.as_ref(),
-            &[curve.bump],
-        ];
-        let signer_seeds = &[&seeds[..]];
+        // Cost on the curve, then the smallest payment that covers it after the fee
+        let clock = Clock::get()?;
+        let fee_bps = curve.buy_fee_bps_at(clock.slot);
+        let fill = curve.fill_buy_exact(token_amount, curve.segments(&ctx.accounts.curve_config)?)?;
+        let sol_amount = fees::gross_for_net(fill.sol, fee_bps)?;
+        let fee = sol_amount.checked_sub(fill.sol).ok_or(ErrorCode::MathOverflow)?;
         
-        let mint_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.token_mint.to_account_info(),
-                to: ctx.accounts.buyer_token_account.to_account_info(),
-                authority: ctx.accounts.bonding_curve.to_account_info(),
-            },
-            signer_seeds,
+        require!(sol_amount <= max_sol_in, ErrorCode::SlippageExceeded);
+        // An exact amount can't be capped, so it may reach the threshold but not pass it
+        require!(
+            curve.sol_reserves.checked_add(fill.sol).ok_or(ErrorCode::MathOverflow)? <= platform.graduation_threshold,
+            ErrorCode::GraduationThresholdExceeded
         );
-        token::mint_to(mint_ctx, tokens_to_mint)?;
+        let allocation = curve.check_allowlist(
+            clock.unix_timestamp,
+            &ctx.accounts.buyer.key(),
+            allowlist_proof.as_ref(),
+        )?;
+        curve.check_launch_limits(
+            clock.unix_timestamp,
+            token_amount,
+            allocation,
+            ctx.accounts.wallet_purchases.as_deref_mut(),
+        )?;
         
-        // Update curve state
-        curve.token_supply = curve.token_supply.checked_add(tokens_to_mint).ok_or(ErrorCode::MathOverflow)?;
-        curve.sol_reserves = curve.sol_reserves.checked_add(sol_after_fee).ok_or(ErrorCode::MathOverflow)?;
+        let launch_fee = curve.launch_fee_of(fee, fee_bps);
+        let split = ctx.accounts.split_fee(fee, launch_fee)?;
+        ctx.accounts.settle(&fill, &split)?;
         
         emit!(TokensBought {
             buyer: ctx.accounts.buyer.key(),
-            token_mint: curve.token_mint,
+            token_mint: ctx.accounts.bonding_curve.token_mint,
             sol_amount,
-            tokens_received: tokens_to_mint,
+            tokens_received: token_amount,
             fee,
+            fee_bps,
+            launch_fee,
+            platform_fee: split.platform,
+            creator_fee: split.creator,
+            referrer_fee: split.referrer,
+            referrer: ctx.accounts.referral.as_ref().map(|referral| referral.owner),
+            sol_refunded: 0,
+            price: ctx.accounts.bonding_curve.current_price()?,
         });
         
+        ctx.accounts.complete_at_threshold()?;
+        
         Ok(())
     }
 
     /// Sell tokens for SOL
     pub fn sell(ctx: Context<Sell>, token_amount: u64, min_sol_out: u64) -> Result<()> {
-        let curve = &mut ctx.accounts.bonding_curve;
+        let curve = &ctx.accounts.bonding_curve;
+        let platform = &ctx.accounts.platform_config;
         
-        require!(!curve.graduated, ErrorCode::CurveGraduated);
-        require!(token_amount > 0, ErrorCode::InvalidAmount);
-        require!(token_amount <= curve.token_supply, ErrorCode::InsufficientSupply);
+        platform.require_active()?;
         
-        // Calculate SOL to return based on bonding curve formula
-        let sol_to_return = calculate_sell_sol(
-            curve.token_supply,
-            curve.max_supply,
-            curve.base_price,
+        // Calculate SOL to return based on bonding curve formula, less the fee
+        let (fill, quote) = curve.quote_sell(
             token_amount,
+            curve.segments(&ctx.accounts.curve_config)?,
+            platform,
         )?;
-        
-        // Calculate fee (1%)
-        let fee = sol_to_return.checked_div(100).ok_or(ErrorCode::MathOverflow)?;
-        let sol_after_fee = sol_to_return.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        let fee = quote.fee;
+        let sol_after_fee = quote.amount_out;
         
         require!(sol_after_fee >= min_sol_out, ErrorCode::SlippageExceeded);
-        require!(sol_to_return <= curve.sol_reserves, ErrorCode::InsufficientReserves);
         
-        // Burn tokens from seller
-        let burn_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Burn {
-                mint: ctx.accounts.token_mint.to_account_info(),
-                from: ctx.accounts.seller_token_account.to_account_info(),
-                authority: ctx.accounts.seller.to_account_info(),
-            },
-        );
-        token::burn(burn_ctx, token_amount)?;
+        let split = ctx.accounts.split_fee(fee)?;
+        ctx.accounts.settle(&fill, &split)?;
         
-        // Transfer SOL from vault to seller
-        let seeds = &[
-            b"bonding_curve",
-            curve.token_mint.as_ref(),
-            &[curve.bump],
-        ];
-        let signer_seeds = &[&seeds[..]];
+        emit!(TokensSold {
+            seller: ctx.accounts.seller.key(),
+            token_mint: ctx.accounts.bonding_curve.token_mint,
+            tokens_sold: token_amount,
+            sol_received: sol_after_fee,
+            fee,
+            fee_bps: ctx.accounts.bonding_curve.sell_fee_bps,
+            platform_fee: split.platform,
+            creator_fee: split.creator,
+            referrer_fee: split.referrer,
+            referrer: ctx.accounts.referral.as_ref().map(|referral| referral.owner),
+            price: ctx.accounts.bonding_curve.current_price()?,
+        });
         
-        **ctx.accounts.sol_vault.to_account_info().try_borrow_mut_lamports()? -= sol_after_fee;
-        **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += sol_after_fee;
+        Ok(())
+    }
+
+    /// Sell for exactly `sol_amount` lamports after the fee, burning at most
+    /// `max_tokens_in` tokens
+    pub fn sell_exact_out(ctx: Context<Sell>, sol_amount: u64, max_tokens_in: u64) -> Result<()> {
+        let curve = &ctx.accounts.bonding_curve;
+        let platform = &ctx.accounts.platform_config;
         
-        // Transfer fee to fee collector
-        **ctx.accounts.sol_vault.to_account_info().try_borrow_mut_lamports()? -= fee;
-        **ctx.accounts.fee_collector.to_account_info().try_borrow_mut_lamports()? += fee;
+        platform.require_active()?;
+        curve.require_trading()?;
+        require!(sol_amount > 0, ErrorCode::InvalidAmount);
         
-        // Update curve state
-        curve.token_supply = curve.token_supply.checked_sub(token_amount).ok_or(ErrorCode::MathOverflow)?;
-        curve.sol_reserves = curve.sol_reserves.checked_sub(sol_to_return).ok_or(ErrorCode::MathOverflow)?;
+        // Gross payout whose fee leaves exactly sol_amount, then the fewest tokens covering it
+        let sol_to_return = fees::gross_for_net(sol_amount, curve.sell_fee_bps)?;
+        let fee = sol_to_return.checked_sub(sol_amount).ok_or(ErrorCode::MathOverflow)?;
+        let fill = curve.fill_sell_exact(sol_to_return, curve.segments(&ctx.accounts.curve_config)?)?;
+        
+        require!(fill.tokens <= max_tokens_in, ErrorCode::SlippageExceeded);
+        
+        let split = ctx.accounts.split_fee(fee)?;
+        ctx.accounts.settle(&fill, &split)?;
         
         emit!(TokensSold {
             seller: ctx.accounts.seller.key(),
-            token_mint: curve.token_mint,
-            tokens_sold: token_amount,
-            sol_received: sol_after_fee,
+            token_mint: ctx.accounts.bonding_curve.token_mint,
+            tokens_sold: fill.tokens,
+            sol_received: sol_amount,
             fee,
+            fee_bps: ctx.accounts.bonding_curve.sell_fee_bps,
+            platform_fee: split.platform,
+            creator_fee: split.creator,
+            referrer_fee: split.referrer,
+            referrer: ctx.accounts.referral.as_ref().map(|referral| referral.owner),
+            price: ctx.accounts.bonding_curve.current_price()?,
         });
         
         Ok(())
     }
 
-    /// Get current token price
-    pub fn get_price(ctx: Context<GetPrice>) -> Result<u64> {
+    /// Pay everything accrued in a curve's creator fee vault to its creator
+    pub fn claim_creator_fees(ctx: Context<ClaimCreatorFees>) -> Result<()> {
+        let creator_fee_vault = ctx.accounts.creator_fee_vault.to_account_info();
+        
+        let amount = vault::available(&creator_fee_vault)?;
+        require!(amount > 0, ErrorCode::NoFeesToClaim);
+        
+        vault::pay_from_vault(&creator_fee_vault, &ctx.accounts.creator.to_account_info(), amount)?;
+        
+        emit!(CreatorFeesClaimed {
+            token_mint: ctx.accounts.bonding_curve.token_mint,
+            creator: ctx.accounts.creator.key(),
+            amount,
+        });
+        
+        Ok(())
+    }
+
+    /// Register a referral code for the signer. Traders pass the code's
+    /// `Referral` account into `buy`/`sell` to credit it the referrer share.
+    pub fn register_referral(ctx: Context<RegisterReferral>, code: String) -> Result<()> {
+        Referral::validate_code(&code)?;
+        
+        let referral = &mut ctx.accounts.referral;
+        referral.owner = ctx.accounts.owner.key();
+        referral.code = code;
+        referral.referred_volume = 0;
+        referral.fees_earned = 0;
+        referral.bump = ctx.bumps.referral;
+        
+        emit!(ReferralRegistered {
+            referral: referral.key(),
+            owner: referral.owner,
+            code: referral.code.clone(),
+        });
+        
+        Ok(())
+    }
+
+    /// Pay a referral's accrued fees to its owner
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+        let referral = ctx.accounts.referral.to_account_info();
+        
+        let amount = vault::available(&referral)?;
+        require!(amount > 0, ErrorCode::NoFeesToClaim);
+        
+        vault::pay_from_vault(&referral, &ctx.accounts.owner.to_account_info(), amount)?;
+        
+        emit!(ReferralFeesClaimed {
+            referral: referral.key(),
+            owner: ctx.accounts.owner.key(),
+            amount,
+        });
+        
+        Ok(())
+    }
+
+    /// Replace the wallets treasury revenue is split between (admin only)
+    pub fn set_revenue_recipients(
+        ctx: Context<SetRevenueRecipients>,
+        recipients: Vec<RevenueRecipient>,
+    ) -> Result<()> {
+        Treasury::validate_recipients(&recipients)?;
+        
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.recipients = recipients;
+        
+        emit!(RevenueRecipientsUpdated {
+            recipients: treasury.recipients.clone(),
+        });
+        
+        Ok(())
+    }
+
+    /// Split the treasury's balance between its recipients by weight.
+    /// Anyone may call it; the recipient wallets are passed, writable and in
+    /// order, as remaining accounts.
+    pub fn distribute_revenue(ctx: Context<DistributeRevenue>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        let recipients = ctx.remaining_accounts;
+        
+        require!(
+            !treasury.recipients.is_empty() && recipients.len() == treasury.recipients.len(),
+            ErrorCode::InvalidRevenueRecipients
+        );
+        for (account, recipient) in recipients.iter().zip(treasury.recipients.iter()) {
+            require_keys_eq!(account.key(), recipient.wallet, ErrorCode::InvalidRevenueRecipients);
+        }
+        
+        let treasury_info = treasury.to_account_info();
+        let available = vault::available(&treasury_info)?;
+        require!(available > 0, ErrorCode::NoRevenueToDistribute);
+        
+        let amounts = treasury.shares(available)?;
+        for (account, amount) in recipients.iter().zip(amounts.iter()) {
+            if *amount > 0 {
+                vault::pay_from_vault(&treasury_info, account, *amount)?;
+            }
+        }
+        
+        let distributed = amounts.iter().sum::<u64>();
+        treasury.total_distributed = treasury
+            .total_distributed
+            .checked_add(distributed)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        emit!(RevenueDistributed {
+            distributed,
+            amounts,
+        });
+        
+        Ok(())
+    }
+
+    /// Get current token price in lamports per whole token, Q64.64
+    pub fn get_price(ctx: Context<GetPrice>) -> Result<u128> {
+        ctx.accounts.bonding_curve.current_price()
+    }
+
+    /// Quote a buy of `sol_amount` lamports (view function)
+    pub fn quote_buy(ctx: Context<GetQuote>, sol_amount: u64, allow_partial: bool) -> Result<TradeQuote> {
         let curve = &ctx.accounts.bonding_curve;
-        calculate_current_price(curve.token_supply, curve.max_supply, curve.base_price)
+        let (_, quote) = curve.quote_buy(
+            sol_amount,
+            allow_partial,
+            curve.segments(&ctx.accounts.curve_config)?,
+            &ctx.accounts.platform_config,
+            Clock::get()?.slot,
+        )?;
+        Ok(quote)
+    }
+
+    /// Quote a sell of `token_amount` tokens (view function)
+    pub fn quote_sell(ctx: Context<GetQuote>, token_amount: u64) -> Result<TradeQuote> {
+        let curve = &ctx.accounts.bonding_curve;
+        let (_, quote) = curve.quote_sell(
+            token_amount,
+            curve.segments(&ctx.accounts.curve_config)?,
+            &ctx.accounts.platform_config,
+        )?;
+        Ok(quote)
     }
 
     /// Mark curve as graduated (called by graduation handler)
@@ -204,97 +601,79 @@ pub mod bonding_curve {
         
         Ok(())
     }
-}
 
-// Helper functions for bonding curve calculations
-fn calculate_buy_tokens(
-    current_supply: u64,
-    max_supply: u64,
-    base_price: u64,
-    sol_amount: u64,
-) -> Result<u64> {
-    // Simplified bonding curve: price = base_price * (1 + supply/max_supply)^2
-    // For buy: integrate to find tokens that can be bought with given SOL
-    
-    // Using approximation for integration
-    let mut tokens = 0u64;
-    let mut remaining_sol = sol_amount;
-    let mut supply = current_supply;
-    
-    // Buy in small increments to approximate the integral
-    let increment = max_supply / 10000; // 0.01% increments
-    
-    while remaining_sol > 0 && supply < max_supply {
-        let price = calculate_current_price(supply, max_supply, base_price)?;
-        let cost = price.checked_mul(increment).ok_or(ErrorCode::MathOverflow)?
-            .checked_div(1_000_000_000).ok_or(ErrorCode::MathOverflow)?; // Normalize
-        
-        if cost > remaining_sol {
-            // Buy partial increment
-            let partial = remaining_sol.checked_mul(1_000_000_000).ok_or(ErrorCode::MathOverflow)?
-                .checked_div(price).ok_or(ErrorCode::MathOverflow)?;
-            tokens = tokens.checked_add(partial).ok_or(ErrorCode::MathOverflow)?;
-            break;
-        }
+    /// Release a graduated curve's SOL reserves to its creator
+    pub fn withdraw_reserves(ctx: Context<WithdrawReserves>) -> Result<()> {
+        let curve = &mut ctx.accounts.bonding_curve;
         
-        tokens = tokens.checked_add(increment).ok_or(ErrorCode::MathOverflow)?;
-        supply = supply.checked_add(increment).ok_or(ErrorCode::MathOverflow)?;
-        remaining_sol = remaining_sol.checked_sub(cost).ok_or(ErrorCode::MathOverflow)?;
-    }
-    
-    Ok(tokens)
-}
-
-fn calculate_sell_sol(
-    current_supply: u64,
-    max_supply: u64,
-    base_price: u64,
-    token_amount: u64,
-) -> Result<u64> {
-    // Reverse of buy calculation
-    let mut sol = 0u64;
-    let mut remaining_tokens = token_amount;
-    let mut supply = current_supply;
-    
-    let increment = max_supply / 10000;
-    
-    while remaining_tokens > 0 && supply > 0 {
-        let price = calculate_current_price(supply, max_supply, base_price)?;
+        require!(curve.graduated, ErrorCode::NotGraduated);
         
-        if remaining_tokens < increment {
-            let partial_sol = price.checked_mul(remaining_tokens).ok_or(ErrorCode::MathOverflow)?
-                .checked_div(1_000_000_000).ok_or(ErrorCode::MathOverflow)?;
-            sol = sol.checked_add(partial_sol).ok_or(ErrorCode::MathOverflow)?;
-            break;
-        }
+        let amount = curve.sol_reserves;
+        vault::pay_from_vault(
+            &ctx.accounts.sol_vault.to_account_info(),
+            &ctx.accounts.recipient,
+            amount,
+        )?;
+        curve.sol_reserves = 0;
         
-        let value = price.checked_mul(increment).ok_or(ErrorCode::MathOverflow)?
-            .checked_div(1_000_000_000).ok_or(ErrorCode::MathOverflow)?;
+        emit!(ReservesWithdrawn {
+            token_mint: curve.token_mint,
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+        });
         
-        sol = sol.checked_add(value).ok_or(ErrorCode::MathOverflow)?;
-        supply = supply.checked_sub(increment).ok_or(ErrorCode::MathOverflow)?;
-        remaining_tokens = remaining_tokens.checked_sub(increment).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
     }
-    
-    Ok(sol)
 }
 
-fn calculate_current_price(supply: u64, max_supply: u64, base_price: u64) -> Result<u64> {
-    // price = base_price * (1 + supply/max_supply)^2
-    let ratio = (supply as u128).checked_mul(1_000_000).ok_or(ErrorCode::MathOverflow)?
-        .checked_div(max_supply as u128).ok_or(ErrorCode::MathOverflow)?;
+// Account contexts
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PlatformConfig::INIT_SPACE,
+        seeds = [b"platform_config"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
     
-    let one_plus_ratio = 1_000_000u128.checked_add(ratio).ok_or(ErrorCode::MathOverflow)?;
-    let squared = one_plus_ratio.checked_mul(one_plus_ratio).ok_or(ErrorCode::MathOverflow)?
-        .checked_div(1_000_000).ok_or(ErrorCode::MathOverflow)?;
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// Only the program's upgrade authority may create the config, so the
+    /// singleton can't be taken by whoever calls first after deployment
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::BondingCurve>,
     
-    let price = (base_price as u128).checked_mul(squared).ok_or(ErrorCode::MathOverflow)?
-        .checked_div(1_000_000).ok_or(ErrorCode::MathOverflow)?;
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
     
-    Ok(price as u64)
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    pub admin: Signer<'info>,
 }
 
-// Account contexts
 #[derive(Accounts)]
 pub struct InitializeCurve<'info> {
     #[account(
@@ -312,16 +691,92 @@ pub struct InitializeCurve<'info> {
     #[account(mut)]
     pub creator: Signer<'info>,
     
-    /// CHECK: Fee collector account
-    pub fee_collector: AccountInfo<'info>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
     
-    /// CHECK: SOL vault PDA
+    /// Program-owned, rent-exempt SOL vault PDA
     #[account(
-        mut,
+        init,
+        payer = creator,
+        space = 8 + SolVault::INIT_SPACE,
         seeds = [b"sol_vault", token_mint.key().as_ref()],
         bump
     )]
-    pub sol_vault: AccountInfo<'info>,
+    pub sol_vault: Account<'info, SolVault>,
+    
+    /// Program-owned PDA holding the creator's share of trading fees
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + CreatorFeeVault::INIT_SPACE,
+        seeds = [b"creator_fee_vault", token_mint.key().as_ref()],
+        bump
+    )]
+    pub creator_fee_vault: Account<'info, CreatorFeeVault>,
+    
+    /// Segments for piecewise curves, omitted otherwise
+    pub curve_config: Option<Account<'info, CurveConfig>>,
+    
+    /// Receives the creation fee
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    /// CHECK: Token factory config, which sets the creation fee
+    #[account(
+        seeds = [b"factory_config"],
+        bump,
+        seeds::program = token_factory::ID,
+        owner = token_factory::ID
+    )]
+    pub factory_config: UncheckedAccount<'info>,
+    
+    /// CHECK: The creator's token factory partner entry; waives the creation fee
+    #[account(
+        seeds = [b"partner", creator.key().as_ref()],
+        bump,
+        seeds::program = token_factory::ID,
+        owner = token_factory::ID
+    )]
+    pub partner: Option<UncheckedAccount<'info>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeCurve<'info> {
+    /// Take the token factory's creation fee from the creator into the
+    /// treasury, nothing for partners. Returns the fee charged.
+    fn charge_creation_fee(&self) -> Result<u64> {
+        if self.partner.is_some() {
+            return Ok(0);
+        }
+        
+        let creation_fee = token_factory::creation_fee(&self.factory_config)?;
+        if creation_fee > 0 {
+            let transfer_ctx = CpiContext::new(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.creator.to_account_info(),
+                    to: self.treasury.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(transfer_ctx, creation_fee)?;
+        }
+        Ok(creation_fee)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(sqrt_start_price: u128, segments: Vec<CurveSegment>)]
+pub struct CreateCurveConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CurveConfig::INIT_SPACE,
+    )]
+    pub curve_config: Account<'info, CurveConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
@@ -341,28 +796,153 @@ pub struct Buy<'info> {
     #[account(mut)]
     pub buyer: Signer<'info>,
     
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
     #[account(
         mut,
         constraint = buyer_token_account.mint == token_mint.key()
     )]
     pub buyer_token_account: Account<'info, TokenAccount>,
     
-    /// CHECK: SOL vault PDA
     #[account(
         mut,
         seeds = [b"sol_vault", token_mint.key().as_ref()],
-        bump
+        bump = sol_vault.bump
     )]
-    pub sol_vault: AccountInfo<'info>,
+    pub sol_vault: Account<'info, SolVault>,
     
-    /// CHECK: Fee collector account
-    #[account(mut)]
+    /// CHECK: Must be the curve's fee collector, the treasury
+    #[account(mut, address = bonding_curve.fee_collector @ ErrorCode::InvalidFeeCollector)]
     pub fee_collector: AccountInfo<'info>,
     
+    #[account(
+        mut,
+        seeds = [b"creator_fee_vault", token_mint.key().as_ref()],
+        bump = creator_fee_vault.bump
+    )]
+    pub creator_fee_vault: Account<'info, CreatorFeeVault>,
+    
+    /// Optional referral, accrues the referrer share of the fee
+    #[account(
+        mut,
+        seeds = [b"referral", referral.code.as_bytes()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Account<'info, Referral>>,
+    
+    /// The buyer's purchase total, required while launch limits or an
+    /// allowlist allocation apply
+    #[account(
+        mut,
+        seeds = [b"wallet_purchases", token_mint.key().as_ref(), buyer.key().as_ref()],
+        bump = wallet_purchases.bump
+    )]
+    pub wallet_purchases: Option<Account<'info, WalletPurchases>>,
+    
+    /// Segments for piecewise curves, omitted otherwise
+    pub curve_config: Option<Account<'info, CurveConfig>>,
+    
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+impl<'info> Buy<'info> {
+    /// Split the normal part of the fee as usual and route the `launch_fee`
+    /// part of it to the launch schedule's recipient
+    fn split_fee(&self, fee: u64, launch_fee: u64) -> Result<FeeSplit> {
+        if let Some(referral) = &self.referral {
+            require_keys_neq!(referral.owner, self.buyer.key(), ErrorCode::SelfReferral);
+        }
+        let normal_fee = fee.checked_sub(launch_fee).ok_or(ErrorCode::MathOverflow)?;
+        let mut split = self.platform_config.split_fee(normal_fee, self.referral.is_some())?;
+        self.bonding_curve.route_launch_fee(&mut split, launch_fee)?;
+        Ok(split)
+    }
+    
+    /// Take the fill's SOL and the fee legs from the buyer, mint the fill's
+    /// tokens to them and record the fill on the curve
+    fn settle(&mut self, fill: &Fill, split: &FeeSplit) -> Result<()> {
+        // Transfer SOL from buyer to curve
+        let transfer_ctx = CpiContext::new(
+            self.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: self.buyer.to_account_info(),
+                to: self.sol_vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(transfer_ctx, fill.sol)?;
+        
+        // Transfer the fee legs to the fee collector, creator fee vault and referral
+        self.pay_fee(self.fee_collector.to_account_info(), split.platform)?;
+        self.pay_fee(self.creator_fee_vault.to_account_info(), split.creator)?;
+        if let Some(referral) = &self.referral {
+            self.pay_fee(referral.to_account_info(), split.referrer)?;
+        }
+        if let Some(referral) = &mut self.referral {
+            let volume = fill.sol.checked_add(split.total()).ok_or(ErrorCode::MathOverflow)?;
+            referral.record(volume, split.referrer)?;
+        }
+        
+        // Mint tokens to buyer
+        let seeds = &[
+            b"bonding_curve",
+            self.bonding_curve.token_mint.as_ref(),
+            &[self.bonding_curve.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        
+        let mint_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            MintTo {
+                mint: self.token_mint.to_account_info(),
+                to: self.buyer_token_account.to_account_info(),
+                authority: self.bonding_curve.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(mint_ctx, fill.tokens)?;
+        
+        // Update curve state
+        self.bonding_curve.apply_buy(fill)?;
+        
+        vault::check_vault_covers_reserves(&self.sol_vault.to_account_info(), &self.bonding_curve)
+    }
+    
+    fn pay_fee(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let transfer_ctx = CpiContext::new(
+            self.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: self.buyer.to_account_info(),
+                to,
+            },
+        );
+        anchor_lang::system_program::transfer(transfer_ctx, amount)
+    }
+    
+    /// Stop trading once reserves reach the graduation threshold, leaving the
+    /// curve for the graduation program to migrate
+    fn complete_at_threshold(&mut self) -> Result<()> {
+        let curve = &mut self.bonding_curve;
+        if curve.sol_reserves < self.platform_config.graduation_threshold {
+            return Ok(());
+        }
+        
+        curve.complete = true;
+        
+        emit!(CurveCompleted {
+            token_mint: curve.token_mint,
+            sol_reserves: curve.sol_reserves,
+            token_supply: curve.token_supply,
+        });
+        
+        Ok(())
+    }
+}
+
 #[derive(Accounts)]
 pub struct Sell<'info> {
     #[account(
@@ -378,28 +958,188 @@ pub struct Sell<'info> {
     #[account(mut)]
     pub seller: Signer<'info>,
     
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
     #[account(
         mut,
         constraint = seller_token_account.mint == token_mint.key()
     )]
     pub seller_token_account: Account<'info, TokenAccount>,
     
-    /// CHECK: SOL vault PDA
     #[account(
         mut,
         seeds = [b"sol_vault", token_mint.key().as_ref()],
-        bump
+        bump = sol_vault.bump
     )]
-    pub sol_vault: AccountInfo<'info>,
+    pub sol_vault: Account<'info, SolVault>,
     
-    /// CHECK: Fee collector account
-    #[account(mut)]
+    /// CHECK: Must be the curve's fee collector, the treasury
+    #[account(mut, address = bonding_curve.fee_collector @ ErrorCode::InvalidFeeCollector)]
     pub fee_collector: AccountInfo<'info>,
     
+    #[account(
+        mut,
+        seeds = [b"creator_fee_vault", token_mint.key().as_ref()],
+        bump = creator_fee_vault.bump
+    )]
+    pub creator_fee_vault: Account<'info, CreatorFeeVault>,
+    
+    /// Optional referral, accrues the referrer share of the fee
+    #[account(
+        mut,
+        seeds = [b"referral", referral.code.as_bytes()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Account<'info, Referral>>,
+    
+    /// Segments for piecewise curves, omitted otherwise
+    pub curve_config: Option<Account<'info, CurveConfig>>,
+    
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+impl<'info> Sell<'info> {
+    fn split_fee(&self, fee: u64) -> Result<FeeSplit> {
+        if let Some(referral) = &self.referral {
+            require_keys_neq!(referral.owner, self.seller.key(), ErrorCode::SelfReferral);
+        }
+        self.platform_config.split_fee(fee, self.referral.is_some())
+    }
+    
+    /// Burn the fill's tokens from the seller, pay out the fill's SOL less
+    /// the fee to them and the fee legs to their recipients, and record the
+    /// fill
+    fn settle(&mut self, fill: &Fill, split: &FeeSplit) -> Result<()> {
+        let sol_after_fee = fill.sol.checked_sub(split.total()).ok_or(ErrorCode::MathOverflow)?;
+        
+        // Burn tokens from seller
+        let burn_ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                mint: self.token_mint.to_account_info(),
+                from: self.seller_token_account.to_account_info(),
+                authority: self.seller.to_account_info(),
+            },
+        );
+        token::burn(burn_ctx, fill.tokens)?;
+        
+        // Transfer SOL from vault to seller
+        let sol_vault = self.sol_vault.to_account_info();
+        vault::pay_from_vault(&sol_vault, &self.seller.to_account_info(), sol_after_fee)?;
+        
+        // Transfer the fee legs to the fee collector, creator fee vault and referral
+        vault::pay_from_vault(&sol_vault, &self.fee_collector, split.platform)?;
+        vault::pay_from_vault(&sol_vault, &self.creator_fee_vault.to_account_info(), split.creator)?;
+        if let Some(referral) = &mut self.referral {
+            vault::pay_from_vault(&sol_vault, &referral.to_account_info(), split.referrer)?;
+            referral.record(fill.sol, split.referrer)?;
+        }
+        
+        // Update curve state
+        self.bonding_curve.apply_sell(fill)?;
+        
+        vault::check_vault_covers_reserves(&self.sol_vault.to_account_info(), &self.bonding_curve)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimCreatorFees<'info> {
+    #[account(
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump,
+        has_one = creator @ ErrorCode::Unauthorized
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(
+        mut,
+        seeds = [b"creator_fee_vault", bonding_curve.token_mint.as_ref()],
+        bump = creator_fee_vault.bump
+    )]
+    pub creator_fee_vault: Account<'info, CreatorFeeVault>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeWalletPurchases<'info> {
+    #[account(
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(
+        init,
+        payer = wallet,
+        space = 8 + WalletPurchases::INIT_SPACE,
+        seeds = [b"wallet_purchases", bonding_curve.token_mint.as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub wallet_purchases: Account<'info, WalletPurchases>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct RegisterReferral<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Referral::INIT_SPACE,
+        seeds = [b"referral", code.as_bytes()],
+        bump
+    )]
+    pub referral: Account<'info, Referral>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"referral", referral.code.as_bytes()],
+        bump = referral.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub referral: Account<'info, Referral>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRevenueRecipients<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeRevenue<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
 #[derive(Accounts)]
 pub struct GetPrice<'info> {
     #[account(
@@ -409,6 +1149,21 @@ pub struct GetPrice<'info> {
     pub bonding_curve: Account<'info, BondingCurve>,
 }
 
+#[derive(Accounts)]
+pub struct GetQuote<'info> {
+    #[account(
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Segments for piecewise curves, omitted otherwise
+    pub curve_config: Option<Account<'info, CurveConfig>>,
+}
+
 #[derive(Accounts)]
 pub struct Graduate<'info> {
     #[account(
@@ -418,11 +1173,69 @@ pub struct Graduate<'info> {
     )]
     pub bonding_curve: Account<'info, BondingCurve>,
     
-    /// CHECK: Only graduation handler can call
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Graduation program PDA or platform admin
+    #[account(constraint = platform_config.is_graduation_handler(&graduation_handler.key()) @ ErrorCode::Unauthorized)]
+    pub graduation_handler: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawReserves<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault", bonding_curve.token_mint.as_ref()],
+        bump = sol_vault.bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+    
+    /// CHECK: Must be the curve creator
+    #[account(mut, address = bonding_curve.creator)]
+    pub recipient: AccountInfo<'info>,
+    
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Graduation program PDA or platform admin
+    #[account(constraint = platform_config.is_graduation_handler(&graduation_handler.key()) @ ErrorCode::Unauthorized)]
     pub graduation_handler: Signer<'info>,
 }
 
 // State
+/// Singleton platform parameters (seeds: `["platform_config"]`)
+#[account]
+#[derive(InitSpace)]
+pub struct PlatformConfig {
+    pub admin: Pubkey,
+    /// The treasury PDA; copied into every new curve
+    pub fee_collector: Pubkey,
+    /// The graduation program's PDA, allowed to graduate curves and
+    /// release their reserves
+    pub graduation_authority: Pubkey,
+    /// Highest buy or sell fee a new curve may charge
+    pub max_fee_bps: u16,
+    /// Creator's and referrer's cut of each trade fee, in basis points of
+    /// the fee; the treasury keeps the rest
+    pub creator_fee_share_bps: u16,
+    pub referrer_fee_share_bps: u16,
+    /// Trading fee of the pool a graduated curve migrates into
+    pub post_graduation_fee_bps: u16,
+    /// SOL reserves, in lamports, at which a curve graduates
+    pub graduation_threshold: u64,
+    pub min_base_price: u64,
+    pub max_base_price: u64,
+    pub paused: bool,
+    pub bump: u8,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct BondingCurve {
@@ -436,6 +1249,104 @@ pub struct BondingCurve {
     pub graduated: bool,
     pub created_at: i64,
     pub bump: u8,
+    pub curve_kind: CurveKind,
+    /// Current sqrt price (Q64.64), piecewise curves only
+    pub sqrt_price: u128,
+    /// Live x * y = k reserves, constant product curves only
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    /// Reserves reached the graduation threshold; trading has stopped
+    pub complete: bool,
+    /// Fees charged on buys and sells, fixed at creation
+    pub buy_fee_bps: u16,
+    pub sell_fee_bps: u16,
+    /// Fee tier the migrated pool must be created with, copied from the
+    /// platform config at creation
+    pub post_graduation_fee_bps: u16,
+    /// Slot the curve was created in; the launch fee decays from here
+    pub launch_slot: u64,
+    /// Anti-sniper surcharge on early buys, if any
+    pub launch_fee: Option<LaunchFeeSchedule>,
+    /// Caps on early buys, if any
+    pub launch_limits: Option<LaunchLimits>,
+    /// Presale restricted to allowlisted wallets, if any
+    pub allowlist: Option<AllowlistPhase>,
+}
+
+/// Holds a curve's SOL reserves. Owned by this program so outflows can
+/// debit it directly; see `vault::pay_from_vault`.
+#[account]
+#[derive(InitSpace)]
+pub struct SolVault {
+    pub bump: u8,
+}
+
+/// Holds a curve's accrued creator fees until `claim_creator_fees`
+#[account]
+#[derive(InitSpace)]
+pub struct CreatorFeeVault {
+    pub bump: u8,
+}
+
+/// Tokens a wallet bought from a curve while its launch limits or an
+/// allowlist allocation applied
+/// (seeds: `["wallet_purchases", token_mint, wallet]`)
+#[account]
+#[derive(InitSpace)]
+pub struct WalletPurchases {
+    pub tokens_bought: u64,
+    pub bump: u8,
+}
+
+/// A referral code (seeds: `["referral", code]`). Holds the owner's accrued
+/// referrer fees until `claim_referral_fees`.
+#[account]
+#[derive(InitSpace)]
+pub struct Referral {
+    pub owner: Pubkey,
+    #[max_len(MAX_REFERRAL_CODE_LEN)]
+    pub code: String,
+    /// Lamports traded through this referral, fees included
+    pub referred_volume: u64,
+    /// Referrer fees credited, claimed or not
+    pub fees_earned: u64,
+    pub bump: u8,
+}
+
+/// Collects platform fees from every curve and the pump pools (seeds:
+/// `["treasury"]`) until `distribute_revenue` pays them out
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    #[max_len(MAX_REVENUE_RECIPIENTS)]
+    pub recipients: Vec<RevenueRecipient>,
+    /// Lamports paid out to recipients so far
+    pub total_distributed: u64,
+    pub bump: u8,
+}
+
+/// A wallet receiving `weight` parts of each treasury distribution
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct RevenueRecipient {
+    pub wallet: Pubkey,
+    pub weight: u16,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CurveConfig {
+    pub authority: Pubkey,
+    /// Sqrt price (Q64.64) where the first segment starts
+    pub sqrt_start_price: u128,
+    #[max_len(MAX_CURVE_SEGMENTS)]
+    pub segments: Vec<CurveSegment>,
+}
+
+/// Constant liquidity from the previous segment's sqrt price up to `sqrt_price`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct CurveSegment {
+    pub sqrt_price: u128,
+    pub liquidity: u128,
 }
 
 // Events
@@ -445,6 +1356,23 @@ pub struct CurveInitialized {
     pub creator: Pubkey,
     pub base_price: u64,
     pub max_supply: u64,
+    pub curve_kind: CurveKind,
+    pub buy_fee_bps: u16,
+    pub sell_fee_bps: u16,
+    pub post_graduation_fee_bps: u16,
+    pub launch_fee: Option<LaunchFeeSchedule>,
+    pub launch_limits: Option<LaunchLimits>,
+    pub allowlist: Option<AllowlistPhase>,
+    /// Lamports charged into the treasury, 0 for partners
+    pub creation_fee: u64,
+}
+
+#[event]
+pub struct CurveConfigCreated {
+    pub curve_config: Pubkey,
+    pub authority: Pubkey,
+    pub sqrt_start_price: u128,
+    pub segment_count: u8,
 }
 
 #[event]
@@ -454,6 +1382,18 @@ pub struct TokensBought {
     pub sol_amount: u64,
     pub tokens_received: u64,
     pub fee: u64,
+    /// Buy fee rate the fee was charged at, launch surcharge included
+    pub fee_bps: u16,
+    /// Part of `fee` that was launch surcharge, paid to the creator or the
+    /// treasury on top of their normal share
+    pub launch_fee: u64,
+    /// How `fee` was divided
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    pub referrer_fee: u64,
+    pub referrer: Option<Pubkey>,
+    pub sol_refunded: u64,
+    pub price: u128,
 }
 
 #[event]
@@ -463,6 +1403,21 @@ pub struct TokensSold {
     pub tokens_sold: u64,
     pub sol_received: u64,
     pub fee: u64,
+    /// Sell fee rate the fee was charged at
+    pub fee_bps: u16,
+    /// How `fee` was divided
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    pub referrer_fee: u64,
+    pub referrer: Option<Pubkey>,
+    pub price: u128,
+}
+
+#[event]
+pub struct CurveCompleted {
+    pub token_mint: Pubkey,
+    pub sol_reserves: u64,
+    pub token_supply: u64,
 }
 
 #[event]
@@ -472,6 +1427,60 @@ pub struct CurveGraduated {
     pub sol_reserves: u64,
 }
 
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub fee_collector: Pubkey,
+    pub max_fee_bps: u16,
+    pub creator_fee_share_bps: u16,
+    pub referrer_fee_share_bps: u16,
+    pub post_graduation_fee_bps: u16,
+    pub graduation_threshold: u64,
+    pub min_base_price: u64,
+    pub max_base_price: u64,
+    pub paused: bool,
+}
+
+#[event]
+pub struct ReservesWithdrawn {
+    pub token_mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CreatorFeesClaimed {
+    pub token_mint: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralRegistered {
+    pub referral: Pubkey,
+    pub owner: Pubkey,
+    pub code: String,
+}
+
+#[event]
+pub struct ReferralFeesClaimed {
+    pub referral: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RevenueRecipientsUpdated {
+    pub recipients: Vec<RevenueRecipient>,
+}
+
+#[event]
+pub struct RevenueDistributed {
+    pub distributed: u64,
+    /// Paid to each recipient, in recipient order
+    pub amounts: Vec<u64>,
+}
+
 // Errors
 #[error_code]
 pub enum ErrorCode {
@@ -495,4 +1504,56 @@ pub enum ErrorCode {
     InsufficientReserves,
     #[msg("Already graduated")]
     AlreadyGraduated,
+    #[msg("Invalid curve parameters")]
+    InvalidCurveParams,
+    #[msg("Curve config does not match the curve")]
+    CurveConfigMismatch,
+    #[msg("SOL vault does not cover reserves plus rent")]
+    VaultUndercollateralized,
+    #[msg("Curve has not graduated")]
+    NotGraduated,
+    #[msg("Invalid platform config")]
+    InvalidConfig,
+    #[msg("Platform is paused")]
+    PlatformPaused,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Fee collector does not match the curve")]
+    InvalidFeeCollector,
+    #[msg("Curve is complete, waiting for migration")]
+    CurveComplete,
+    #[msg("Buy would pass the graduation threshold")]
+    GraduationThresholdExceeded,
+    #[msg("Fee exceeds the platform maximum")]
+    FeeTooHigh,
+    #[msg("Traders cannot refer themselves")]
+    SelfReferral,
+    #[msg("No fees to claim")]
+    NoFeesToClaim,
+    #[msg("Referral codes are 1-16 letters, digits, '-' or '_'")]
+    InvalidReferralCode,
+    #[msg("Revenue recipients are missing, duplicated or out of order")]
+    InvalidRevenueRecipients,
+    #[msg("Treasury has no revenue to distribute")]
+    NoRevenueToDistribute,
+    #[msg("Launch fee must start above the buy fee, at most 50%, and last at least a slot")]
+    InvalidLaunchFee,
+    #[msg("Launch limits need a duration, a per-buy cap and a wallet cap of at most 100%")]
+    InvalidLaunchLimits,
+    #[msg("Buy exceeds the launch per-transaction cap")]
+    LaunchBuyTooLarge,
+    #[msg("Buy takes the wallet past its launch cap")]
+    LaunchWalletCapExceeded,
+    #[msg("Wallet purchases account required during the launch window")]
+    WalletPurchasesRequired,
+    #[msg("Allowlist phase must end in the future")]
+    InvalidAllowlist,
+    #[msg("Allowlist proof required during the allowlist phase")]
+    AllowlistProofRequired,
+    #[msg("Buyer is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Buy takes the wallet past its allowlist allocation")]
+    AllowlistAllocationExceeded,
+    #[msg("Not a token factory FactoryConfig account")]
+    InvalidFactoryConfig,
 }
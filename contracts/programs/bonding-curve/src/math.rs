@@ -0,0 +1,165 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub use wide::U256;
+
+// Kept out of scope of the anchor prelude, whose `Result` alias clashes with
+// the one construct_uint! expects. The allowed lints fire on the macro's
+// own expansion.
+#[allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+mod wide {
+    use uint::construct_uint;
+
+    construct_uint! {
+        /// 256-bit unsigned integer for intermediate curve math
+        pub struct U256(4);
+    }
+}
+
+/// Token base units per whole token (9 decimals). Prices are quoted in
+/// lamports per whole token.
+pub const TOKEN_PRECISION: u64 = 1_000_000_000;
+
+/// Narrow a 256-bit intermediate back to u64
+pub fn to_u64(value: U256) -> Result<u64> {
+    require!(value <= U256::from(u64::MAX), ErrorCode::MathOverflow);
+    Ok(value.low_u64())
+}
+
+/// Narrow a 256-bit intermediate back to u128
+pub fn to_u128(value: U256) -> Result<u128> {
+    require!(value <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+    Ok(value.as_u128())
+}
+
+/// x^3, erroring instead of wrapping
+pub fn cube(x: U256) -> Result<U256> {
+    x.checked_mul(x)
+        .and_then(|x2| x2.checked_mul(x))
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// floor(a * b / denominator) without overflowing on a * b.
+///
+/// Splits `a` into quotient and remainder by `denominator` so only
+/// `remainder * b` has to fit, which it always does when `b` is a u64 and
+/// `denominator` is below 2^192.
+pub fn mul_div_floor(a: U256, b: u64, denominator: U256) -> Result<U256> {
+    require!(!denominator.is_zero(), ErrorCode::MathOverflow);
+    let b = U256::from(b);
+    let (quotient, remainder) = a.div_mod(denominator);
+    let whole = quotient
+        .checked_mul(b)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    let fraction = remainder
+        .checked_mul(b)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?
+        / denominator;
+    whole
+        .checked_add(fraction)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// ceil(a * b / denominator), split the same way as `mul_div_floor`
+pub fn mul_div_ceil(a: U256, b: u64, denominator: U256) -> Result<U256> {
+    require!(!denominator.is_zero(), ErrorCode::MathOverflow);
+    let b = U256::from(b);
+    let (quotient, remainder) = a.div_mod(denominator);
+    let whole = quotient
+        .checked_mul(b)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    let (fraction, rest) = remainder
+        .checked_mul(b)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?
+        .div_mod(denominator);
+    let fraction = if rest.is_zero() { fraction } else { fraction + U256::one() };
+    whole
+        .checked_add(fraction)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// Integer cube root, rounded down.
+///
+/// Newton's method started from a power of two above the root. Every
+/// iterate stays >= floor(cbrt(n)), and the sequence stops decreasing
+/// exactly when it reaches it, so the loop runs a bounded (~log bits)
+/// number of times.
+pub fn icbrt(n: U256) -> U256 {
+    if n.is_zero() {
+        return U256::zero();
+    }
+
+    let mut x = U256::one() << n.bits().div_ceil(3);
+    loop {
+        let y = (x * U256::from(2u8) + n / (x * x)) / U256::from(3u8);
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Integer square root, rounded down. Same Newton scheme as `icbrt`.
+pub fn isqrt(n: U256) -> U256 {
+    if n.is_zero() {
+        return U256::zero();
+    }
+
+    let mut x = U256::one() << n.bits().div_ceil(2);
+    loop {
+        let y = (x + n / x) >> 1;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// 1.0 in Q64.64
+pub const ONE_Q64: u128 = 1 << 64;
+
+/// ln(2) in Q64.64
+pub const LN2_Q64: u128 = 12_786_308_645_202_655_659;
+
+/// 2^x for x in Q64.64, result in Q64.64, rounded down.
+///
+/// The fractional part is expanded as the Taylor series of e^(f * ln 2),
+/// which converges to full precision in under 30 terms since f * ln 2 < 0.7.
+pub fn exp2_q64(x: u128) -> Result<u128> {
+    let whole = x >> 64;
+    require!(whole < 63, ErrorCode::MathOverflow);
+
+    let y = ((x & (ONE_Q64 - 1)) * LN2_Q64) >> 64;
+    let mut sum = ONE_Q64;
+    let mut term = ONE_Q64;
+    let mut k = 1u128;
+    while term > 0 {
+        term = ((term * y) >> 64) / k;
+        sum += term;
+        k += 1;
+    }
+
+    Ok(sum << whole)
+}
+
+/// log2(x) for x >= 1 in Q64.64, result in Q64.64, rounded down.
+///
+/// Integer part from the bit length, fractional bits by repeated squaring.
+pub fn log2_q64(x: u128) -> Result<u128> {
+    require!(x >= ONE_Q64, ErrorCode::MathOverflow);
+
+    let whole = 127 - x.leading_zeros() as u128 - 64;
+    let mut result = whole << 64;
+    let mut y = U256::from(x >> whole);
+    let two = U256::from(ONE_Q64) << 1;
+    for bit in (0..64).rev() {
+        y = (y * y) >> 64;
+        if y >= two {
+            y >>= 1;
+            result |= 1 << bit;
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+
+use crate::curve::Fill;
+use crate::fees::BPS_DENOMINATOR;
+use crate::math::{to_u64, U256};
+use crate::{fees, BondingCurve, CurveConfig, ErrorCode, PlatformConfig};
+
+/// What a buy or sell would do, returned by `quote_buy` / `quote_sell`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TradeQuote {
+    /// Tokens minted by a buy, or lamports paid out by a sell after the fee
+    pub amount_out: u64,
+    pub fee: u64,
+    /// Lamports of a partial buy left with the buyer
+    pub sol_refunded: u64,
+    /// Spot prices in lamports per whole token, Q64.64
+    pub price_before: u128,
+    pub price_after: u128,
+    /// Spot price move in basis points of `price_before`
+    pub price_impact_bps: u64,
+    /// The buy takes supply to max_supply
+    pub hits_max_supply: bool,
+    /// The buy takes reserves to the platform's graduation threshold and
+    /// completes the curve
+    pub triggers_graduation: bool,
+}
+
+impl BondingCurve {
+    /// Price `sol_amount` lamports in, fee included, without touching state.
+    /// `buy` executes exactly this fill.
+    ///
+    /// A buy that would take reserves past the graduation threshold is capped
+    /// at the threshold: it puts exactly the missing reserves into the curve,
+    /// charges those plus the fee and leaves the rest with the buyer.
+    ///
+    /// A buy that would pass max_supply fails, unless `allow_partial` is set:
+    /// then it fills only the remaining supply, charging its cost plus fee
+    /// and leaving the rest of `sol_amount` with the buyer.
+    ///
+    /// The fee is the buy fee at `slot`, launch surcharge included.
+    pub fn quote_buy(
+        &self,
+        sol_amount: u64,
+        allow_partial: bool,
+        config: Option<&CurveConfig>,
+        platform: &PlatformConfig,
+        slot: u64,
+    ) -> Result<(Fill, TradeQuote)> {
+        self.require_trading()?;
+        require!(sol_amount > 0, ErrorCode::InvalidAmount);
+
+        let fee_bps = self.buy_fee_bps_at(slot);
+        let fee = fees::fee_on(sol_amount, fee_bps)?;
+        let mut budget = sol_amount.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        let mut charged = sol_amount;
+
+        let room = platform.graduation_threshold.saturating_sub(self.sol_reserves);
+        if budget > room {
+            budget = room;
+            charged = fees::gross_for_net(budget, fee_bps)?;
+        }
+        let mut fill = self.fill_buy(budget, config)?;
+
+        let remaining = self.max_supply.saturating_sub(self.token_supply);
+        if fill.tokens > remaining {
+            require!(allow_partial && remaining > 0, ErrorCode::MaxSupplyExceeded);
+            fill = self.fill_buy_exact(remaining, config)?;
+            // The cost rounds up, so it can top the budget by a lamport
+            // that the budget is known to cover
+            fill.sol = fill.sol.min(budget);
+            charged = fees::gross_for_net(fill.sol, fee_bps)?;
+        }
+
+        let mut after = self.clone();
+        after.apply_buy(&fill)?;
+
+        let fee = charged.checked_sub(fill.sol).ok_or(ErrorCode::MathOverflow)?;
+        let mut quote = self.quote(&after, fill.tokens, fee, platform)?;
+        quote.sol_refunded = sol_amount - charged;
+        Ok((fill, quote))
+    }
+
+    /// Price selling `token_amount` tokens without touching state. `sell`
+    /// executes exactly this fill.
+    pub fn quote_sell(
+        &self,
+        token_amount: u64,
+        config: Option<&CurveConfig>,
+        platform: &PlatformConfig,
+    ) -> Result<(Fill, TradeQuote)> {
+        self.require_trading()?;
+        require!(token_amount > 0, ErrorCode::InvalidAmount);
+        require!(token_amount <= self.token_supply, ErrorCode::InsufficientSupply);
+
+        let fill = self.fill_sell(token_amount, config)?;
+        require!(fill.sol <= self.sol_reserves, ErrorCode::InsufficientReserves);
+
+        let fee = fees::fee_on(fill.sol, self.sell_fee_bps)?;
+        let sol_after_fee = fill.sol.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+
+        let mut after = self.clone();
+        after.apply_sell(&fill)?;
+
+        let quote = self.quote(&after, sol_after_fee, fee, platform)?;
+        Ok((fill, quote))
+    }
+
+    /// Trading stops once a curve completes or graduates
+    pub fn require_trading(&self) -> Result<()> {
+        require!(!self.graduated, ErrorCode::CurveGraduated);
+        require!(!self.complete, ErrorCode::CurveComplete);
+        Ok(())
+    }
+
+    fn quote(
+        &self,
+        after: &BondingCurve,
+        amount_out: u64,
+        fee: u64,
+        platform: &PlatformConfig,
+    ) -> Result<TradeQuote> {
+        let price_before = self.current_price()?;
+        let price_after = after.current_price()?;
+        let price_impact_bps = match price_before {
+            0 => 0,
+            _ => {
+                let moved = U256::from(price_after.abs_diff(price_before));
+                to_u64(moved * U256::from(BPS_DENOMINATOR) / U256::from(price_before))
+                    .unwrap_or(u64::MAX)
+            }
+        };
+
+        Ok(TradeQuote {
+            amount_out,
+            fee,
+            sol_refunded: 0,
+            price_before,
+            price_after,
+            price_impact_bps,
+            hits_max_supply: after.token_supply >= self.max_supply,
+            triggers_graduation: after.sol_reserves >= platform.graduation_threshold
+                && self.sol_reserves < platform.graduation_threshold,
+        })
+    }
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, Referral};
+
+/// Longest referral code `register_referral` accepts, in bytes
+pub const MAX_REFERRAL_CODE_LEN: usize = 16;
+
+impl Referral {
+    /// Codes are short ASCII letters, digits, `-` or `_`, so they survive
+    /// URLs and are unambiguous as PDA seeds
+    pub fn validate_code(code: &str) -> Result<()> {
+        require!(
+            !code.is_empty() && code.len() <= MAX_REFERRAL_CODE_LEN,
+            ErrorCode::InvalidReferralCode
+        );
+        require!(
+            code.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            ErrorCode::InvalidReferralCode
+        );
+        Ok(())
+    }
+
+    /// Credit a referred trade of `volume` lamports that paid `fee` to
+    /// this referral
+    pub fn record(&mut self, volume: u64, fee: u64) -> Result<()> {
+        self.referred_volume = self.referred_volume.checked_add(volume).ok_or(ErrorCode::MathOverflow)?;
+        self.fees_earned = self.fees_earned.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+// The token factory program, whose config sets the fee every new curve pays.
+// It depends on this crate, so its accounts are read by hand.
+declare_id!("8SJXJETfrrb8YHCr1FJPDVXy6F3pV2aQ6N8RWB2N3e5h");
+
+/// Anchor discriminator of the factory's `FactoryConfig` account
+const FACTORY_CONFIG_DISCRIMINATOR: [u8; 8] = [29, 197, 255, 232, 22, 128, 67, 26];
+
+/// Lamports a curve pays the treasury on creation: the `FactoryConfig`'s
+/// `creation_fee`, which follows the discriminator
+pub fn creation_fee(factory_config: &AccountInfo) -> Result<u64> {
+    let data = factory_config.try_borrow_data()?;
+    require!(
+        data.len() >= 16 && data[..8] == FACTORY_CONFIG_DISCRIMINATOR,
+        ErrorCode::InvalidFactoryConfig
+    );
+    Ok(u64::from_le_bytes(data[8..16].try_into().unwrap()))
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, RevenueRecipient, Treasury};
+
+/// Most wallets the treasury splits its revenue between
+pub const MAX_REVENUE_RECIPIENTS: usize = 8;
+
+impl Treasury {
+    /// A recipient list needs at least one wallet, no duplicates and no
+    /// zero weights
+    pub fn validate_recipients(recipients: &[RevenueRecipient]) -> Result<()> {
+        require!(
+            !recipients.is_empty() && recipients.len() <= MAX_REVENUE_RECIPIENTS,
+            ErrorCode::InvalidRevenueRecipients
+        );
+        for (i, recipient) in recipients.iter().enumerate() {
+            require!(recipient.weight > 0, ErrorCode::InvalidRevenueRecipients);
+            require!(
+                recipients[..i].iter().all(|other| other.wallet != recipient.wallet),
+                ErrorCode::InvalidRevenueRecipients
+            );
+        }
+        Ok(())
+    }
+
+    /// Each recipient's cut of `amount`, in recipient order. Shares round
+    /// down; the dust stays in the treasury for the next distribution.
+    pub fn shares(&self, amount: u64) -> Result<Vec<u64>> {
+        let total_weight: u128 = self.recipients.iter().map(|r| r.weight as u128).sum();
+        require!(total_weight > 0, ErrorCode::InvalidRevenueRecipients);
+
+        self.recipients
+            .iter()
+            .map(|recipient| {
+                u64::try_from(amount as u128 * recipient.weight as u128 / total_weight)
+                    .map_err(|_| error!(ErrorCode::MathOverflow))
+            })
+            .collect()
+    }
+}
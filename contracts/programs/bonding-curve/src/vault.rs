@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{BondingCurve, ErrorCode};
+
+/// Move `amount` lamports out of a curve's SOL vault or creator fee vault,
+/// a referral or the treasury.
+///
+/// Every SOL outflow goes through here. The vaults are owned by this
+/// program, so their lamports can be debited directly, but never below
+/// their rent-exempt minimum.
+pub fn pay_from_vault(vault: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    require!(amount <= available(vault)?, ErrorCode::InsufficientReserves);
+
+    **vault.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+/// Lamports a vault holds above its rent-exempt minimum
+pub fn available(vault: &AccountInfo) -> Result<u64> {
+    let rent = Rent::get()?.minimum_balance(vault.data_len());
+    Ok(vault.lamports().saturating_sub(rent))
+}
+
+/// Post-trade invariant: the vault still holds the curve's reserves on top of
+/// its rent-exempt minimum, whatever the rounding along the way
+pub fn check_vault_covers_reserves(sol_vault: &AccountInfo, curve: &BondingCurve) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(sol_vault.data_len());
+    let required = curve.sol_reserves.checked_add(rent).ok_or(ErrorCode::MathOverflow)?;
+    require!(sol_vault.lamports() >= required, ErrorCode::VaultUndercollateralized);
+    Ok(())
+}
@@ -1,68 +1,115 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use bonding_curve::program::BondingCurve as BondingCurveProgram;
+use bonding_curve::{BondingCurve, PlatformConfig};
 
 declare_id!("BMH2GPLn8woVeGFKAHwJ3wPpBf7mhxRipPzPm9d6Pbjt");
 
-// Raydium AMM program ID (mainnet/devnet)
-const RAYDIUM_AMM_PROGRAM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+// Raydium CP-Swap program, which graduated curves migrate into
+mod raydium_cp_swap {
+    use super::*;
+    declare_id!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+    /// Anchor discriminator of CP-Swap's `AmmConfig` account
+    const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+
+    /// `AmmConfig` fee rates are in millionths
+    pub const FEE_RATE_PER_BPS: u64 = 100;
+
+    /// Trade fee rate of an `AmmConfig`, the fee tier of every pool created
+    /// with it. Follows the discriminator, `bump: u8`,
+    /// `disable_create_pool: bool` and `index: u16`.
+    pub fn trade_fee_rate(amm_config: &AccountInfo) -> Result<u64> {
+        let data = amm_config.try_borrow_data()?;
+        require!(
+            data.len() >= 20 && data[..8] == AMM_CONFIG_DISCRIMINATOR,
+            ErrorCode::InvalidAmmConfig
+        );
+        Ok(u64::from_le_bytes(data[12..20].try_into().unwrap()))
+    }
+
+    /// Anchor discriminator of CP-Swap's `PoolState` account
+    const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+
+    /// `AmmConfig` a `PoolState` was created with, the first field after
+    /// the discriminator
+    pub fn pool_amm_config(pool: &AccountInfo) -> Result<Pubkey> {
+        let data = pool.try_borrow_data()?;
+        require!(
+            data.len() >= 40 && data[..8] == POOL_STATE_DISCRIMINATOR,
+            ErrorCode::InvalidPool
+        );
+        Ok(Pubkey::new_from_array(data[8..40].try_into().unwrap()))
+    }
+}
 
 #[program]
 pub mod graduation {
     use super::*;
 
     /// Check if token has reached graduation threshold and process if ready
+    /// Permissionless: the curve's state decides, not the caller
     pub fn check_and_graduate(ctx: Context<CheckAndGraduate>) -> Result<()> {
         let bonding_curve = &ctx.accounts.bonding_curve;
         
         require!(!bonding_curve.graduated, ErrorCode::AlreadyGraduated);
         
+        let token_mint = bonding_curve.token_mint;
+        let sol_reserves = bonding_curve.sol_reserves;
+        let token_supply = bonding_curve.token_supply;
+        
         // Calculate market cap (simplified: sol_reserves represents market cap in SOL)
-        let market_cap_lamports = bonding_curve.sol_reserves;
+        let market_cap_lamports = sol_reserves;
         let market_cap_sol = market_cap_lamports / 1_000_000_000; // Convert lamports to SOL
         
-        // Graduation threshold: $69K
-        // Assuming 1 SOL = ~$100 (this should come from oracle in production)
-        let threshold_sol = 690; // 690 SOL ≈ $69K
-        
+        // Graduation threshold comes from the platform config (690 SOL ≈ $69K at launch)
         require!(
-            market_cap_sol >= threshold_sol,
+            market_cap_lamports >= ctx.accounts.platform_config.graduation_threshold,
             ErrorCode::ThresholdNotReached
         );
         
-        // Mark curve as graduated via CPI
+        // Mark curve as graduated via CPI, signed by the graduation authority PDA
         let graduate_cpi = bonding_curve::cpi::accounts::Graduate {
             bonding_curve: ctx.accounts.bonding_curve.to_account_info(),
-            graduation_handler: ctx.accounts.authority.to_account_info(),
+            platform_config: ctx.accounts.platform_config.to_account_info(),
+            graduation_handler: ctx.accounts.graduation_authority.to_account_info(),
         };
         
-        let cpi_ctx = CpiContext::new(
+        let seeds = &[b"graduation_authority".as_ref(), &[ctx.bumps.graduation_authority]];
+        let signer_seeds = &[&seeds[..]];
+        
+        let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.bonding_curve_program.to_account_info(),
             graduate_cpi,
+            signer_seeds,
         );
         
         bonding_curve::cpi::graduate(cpi_ctx)?;
         
         emit!(ReadyForGraduation {
-            token_mint: bonding_curve.token_mint,
+            token_mint,
             market_cap_sol,
-            sol_reserves: bonding_curve.sol_reserves,
-            token_supply: bonding_curve.token_supply,
+            sol_reserves,
+            token_supply,
         });
         
         Ok(())
     }
 
-    /// Migrate liquidity to Raydium (Phase 2: Create pool and add liquidity)
+    /// Migrate liquidity to Raydium (Phase 2: Create pool and add liquidity).
+    /// The pool is created with `amm_config`, whose trade fee must be the
+    /// curve's post-graduation fee tier.
     pub fn migrate_to_raydium(ctx: Context<MigrateToRaydium>) -> Result<()> {
         let bonding_curve = &ctx.accounts.bonding_curve;
         
         require!(bonding_curve.graduated, ErrorCode::NotGraduated);
+        let pool_fee_bps = bonding_curve.post_graduation_fee_bps;
         
         let sol_amount = bonding_curve.sol_reserves;
         let token_amount = bonding_curve.token_supply;
         
         // In production, this would:
-        // 1. Create Raydium pool via CPI to Raydium AMM
+        // 1. Create Raydium pool via CPI to Raydium CP-Swap with amm_config
         // 2. Transfer SOL and tokens to pool
         // 3. Receive LP tokens
         // 4. Burn LP tokens to lock liquidity permanently
@@ -73,6 +120,7 @@ pub mod graduation {
             raydium_pool: ctx.accounts.raydium_pool.key(),
             sol_migrated: sol_amount,
             tokens_migrated: token_amount,
+            pool_fee_bps,
             lp_tokens_burned: true,
         });
         
@@ -86,13 +134,32 @@ pub mod graduation {
         // Only allow if something went wrong with graduation
         require!(bonding_curve.graduated, ErrorCode::NotGraduated);
         
-        // Transfer remaining SOL back to creator
-        **ctx.accounts.sol_vault.to_account_info().try_borrow_mut_lamports()? -= bonding_curve.sol_reserves;
-        **ctx.accounts.creator.to_account_info().try_borrow_mut_lamports()? += bonding_curve.sol_reserves;
+        let token_mint = bonding_curve.token_mint;
+        let amount = bonding_curve.sol_reserves;
+        
+        // The vault is owned by the bonding curve program, so it has to pay out
+        let withdraw_cpi = bonding_curve::cpi::accounts::WithdrawReserves {
+            bonding_curve: ctx.accounts.bonding_curve.to_account_info(),
+            sol_vault: ctx.accounts.sol_vault.to_account_info(),
+            recipient: ctx.accounts.creator.to_account_info(),
+            platform_config: ctx.accounts.platform_config.to_account_info(),
+            graduation_handler: ctx.accounts.graduation_authority.to_account_info(),
+        };
+        
+        let seeds = &[b"graduation_authority".as_ref(), &[ctx.bumps.graduation_authority]];
+        let signer_seeds = &[&seeds[..]];
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.bonding_curve_program.to_account_info(),
+            withdraw_cpi,
+            signer_seeds,
+        );
+        
+        bonding_curve::cpi::withdraw_reserves(cpi_ctx)?;
         
         emit!(EmergencyWithdrawal {
-            token_mint: bonding_curve.token_mint,
-            amount: bonding_curve.sol_reserves,
+            token_mint,
+            amount,
             recipient: ctx.accounts.creator.key(),
         });
         
@@ -103,37 +170,85 @@ pub mod graduation {
 // Account contexts
 #[derive(Accounts)]
 pub struct CheckAndGraduate<'info> {
-    /// CHECK: Bonding curve account (read-only for checking)
-    pub bonding_curve: Account<'info, BondingCurveState>,
+    /// Bonding curve account, marked graduated via CPI
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
     
-    /// CHECK: Authority that can trigger graduation
-    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
     
-    /// CHECK: Bonding curve program
-    pub bonding_curve_program: AccountInfo<'info>,
+    /// CHECK: PDA that signs graduation CPIs into the bonding curve program
+    #[account(seeds = [b"graduation_authority"], bump)]
+    pub graduation_authority: AccountInfo<'info>,
+    
+    pub bonding_curve_program: Program<'info, BondingCurveProgram>,
 }
 
 #[derive(Accounts)]
 pub struct MigrateToRaydium<'info> {
-    /// CHECK: Bonding curve account
-    pub bonding_curve: Account<'info, BondingCurveState>,
+    #[account(
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump,
+        seeds::program = bonding_curve::ID
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
     
-    #[account(mut)]
+    #[account(mut, address = bonding_curve.token_mint)]
     pub token_mint: Account<'info, Mint>,
     
     /// CHECK: SOL vault from bonding curve
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"sol_vault", bonding_curve.token_mint.as_ref()],
+        bump,
+        seeds::program = bonding_curve::ID
+    )]
     pub sol_vault: AccountInfo<'info>,
     
-    /// CHECK: Token vault (tokens from bonding curve)
-    #[account(mut)]
+    /// Token vault (tokens from bonding curve), held by the graduation
+    /// authority so only this program can move it into the pool
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = graduation_authority
+    )]
     pub token_vault: Account<'info, TokenAccount>,
     
-    /// CHECK: Raydium pool account (to be created)
-    #[account(mut)]
+    /// CHECK: PDA that owns the token vault
+    #[account(seeds = [b"graduation_authority"], bump)]
+    pub graduation_authority: AccountInfo<'info>,
+    
+    /// CHECK: CP-Swap pool the liquidity moves into; it must have been
+    /// created with `amm_config`
+    #[account(
+        mut,
+        owner = raydium_cp_swap::ID,
+        constraint = raydium_cp_swap::pool_amm_config(&raydium_pool)? == amm_config.key()
+            @ ErrorCode::PoolConfigMismatch
+    )]
     pub raydium_pool: AccountInfo<'info>,
     
-    /// CHECK: Raydium AMM program
+    /// CHECK: CP-Swap fee config the pool is created with; its trade fee
+    /// must be the curve's post-graduation fee
+    #[account(
+        owner = raydium_cp_swap::ID,
+        constraint = raydium_cp_swap::trade_fee_rate(&amm_config)?
+            == bonding_curve.post_graduation_fee_bps as u64 * raydium_cp_swap::FEE_RATE_PER_BPS
+            @ ErrorCode::PoolFeeMismatch
+    )]
+    pub amm_config: AccountInfo<'info>,
+    
+    /// CHECK: Raydium CP-Swap program
+    #[account(address = raydium_cp_swap::ID)]
     pub raydium_program: AccountInfo<'info>,
     
     #[account(mut)]
@@ -145,34 +260,44 @@ pub struct MigrateToRaydium<'info> {
 
 #[derive(Accounts)]
 pub struct EmergencyWithdraw<'info> {
-    /// CHECK: Bonding curve account
-    pub bonding_curve: Account<'info, BondingCurveState>,
+    /// Bonding curve account, reserves zeroed via CPI
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
     
-    /// CHECK: SOL vault
-    #[account(mut)]
+    /// CHECK: SOL vault of the curve
+    #[account(
+        mut,
+        seeds = [b"sol_vault", bonding_curve.token_mint.as_ref()],
+        bump,
+        seeds::program = bonding_curve_program.key()
+    )]
     pub sol_vault: AccountInfo<'info>,
     
     /// CHECK: Creator (original token creator)
-    #[account(mut)]
+    #[account(mut, address = bonding_curve.creator)]
     pub creator: AccountInfo<'info>,
     
-    /// CHECK: Admin authority
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Platform admin
+    #[account(address = platform_config.admin @ ErrorCode::Unauthorized)]
     pub admin: Signer<'info>,
-}
-
-// Simplified bonding curve state (matches actual program)
-#[account]
-pub struct BondingCurveState {
-    pub token_mint: Pubkey,
-    pub creator: Pubkey,
-    pub base_price: u64,
-    pub token_supply: u64,
-    pub max_supply: u64,
-    pub sol_reserves: u64,
-    pub fee_collector: Pubkey,
-    pub graduated: bool,
-    pub created_at: i64,
-    pub bump: u8,
+    
+    /// CHECK: PDA that signs graduation CPIs into the bonding curve program
+    #[account(seeds = [b"graduation_authority"], bump)]
+    pub graduation_authority: AccountInfo<'info>,
+    
+    pub bonding_curve_program: Program<'info, BondingCurveProgram>,
 }
 
 // Events
@@ -190,6 +315,7 @@ pub struct TokenGraduated {
     pub raydium_pool: Pubkey,
     pub sol_migrated: u64,
     pub tokens_migrated: u64,
+    pub pool_fee_bps: u16,
     pub lp_tokens_burned: bool,
 }
 
@@ -205,34 +331,19 @@ pub struct EmergencyWithdrawal {
 pub enum ErrorCode {
     #[msg("Token has already graduated")]
     AlreadyGraduated,
-    #[msg("Market cap threshold not reached ($69K)")]
+    #[msg("Graduation threshold not reached")]
     ThresholdNotReached,
     #[msg("Token has not graduated yet")]
     NotGraduated,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Pool fee does not match the curve's post-graduation fee")]
+    PoolFeeMismatch,
+    #[msg("Not a Raydium CP-Swap AmmConfig account")]
+    InvalidAmmConfig,
+    #[msg("Pool was not created with the given AmmConfig")]
+    PoolConfigMismatch,
+    #[msg("Not a Raydium CP-Swap PoolState account")]
+    InvalidPool,
 }
 
-// External program CPI interfaces
-pub mod bonding_curve {
-    use super::*;
-    
-    pub mod cpi {
-        use super::*;
-        
-        pub mod accounts {
-            use super::*;
-            
-            pub struct Graduate<'info> {
-                pub bonding_curve: AccountInfo<'info>,
-                pub graduation_handler: AccountInfo<'info>,
-            }
-        }
-        
-        pub fn graduate(
-            _ctx: CpiContext<accounts::Graduate>,
-        ) -> Result<()> {
-            Ok(())
-        }
-    }
-}
@@ -1 +1,9 @@
-pub const INITIAL_PRICE: u64 = 600;       // lamports per one token (without decimal)
\ No newline at end of file
+pub const INITIAL_PRICE: u64 = 600;       // lamports per one token (without decimal)
+
+// The bonding curve program, whose treasury PDA collects the swap fees
+pub mod bonding_curve_program {
+    use anchor_lang::prelude::*;
+    declare_id!("2bkDb7cox1a36tSuGdkTJAmmb4Qmm9yudSTbpL5yqmuz");
+}
+
+pub const TREASURY_SEED: &[u8] = b"treasury";
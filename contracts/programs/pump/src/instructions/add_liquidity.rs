@@ -5,7 +5,7 @@ use anchor_spl::{
 };
 
 use crate::{
-    state::{LiquidityPool, LiquidityPoolAccount, LiquidityProvider},
+    state::{transfer_sol_to_pool, LiquidityPool, LiquidityPoolAccount, LiquidityProvider},
 };
 
 pub fn add_liquidity(ctx: Context<AddLiquidity>, amount_one: u64, amount_two: u64) -> Result<()> {
@@ -38,6 +38,14 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, amount_one: u64, amount_two: u6
         &ctx.accounts.token_program,
     )?;
 
+    // The SOL side backs reserve_two, which remove_liquidity and swaps pay out of
+    transfer_sol_to_pool(
+        ctx.accounts.user.to_account_info(),
+        ctx.accounts.global_account.to_account_info(),
+        amount_two,
+        ctx.accounts.system_program.to_account_info(),
+    )?;
+
     Ok(())
 }
 
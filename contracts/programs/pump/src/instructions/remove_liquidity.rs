@@ -1,19 +1,33 @@
-use anchor_lang::{prelude::*, solana_program::program::invoke_signed};
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
-};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 use crate::state::{LiquidityPool, LiquidityPoolAccount, LiquidityProvider};
-// use raydium_contract_instructions::amm_instruction;
 
-pub fn remove_liquidity(
-    ctx: Context<RemoveLiquidity>,
-    nonce: u8,
-    init_pc_amount: u64,
-) -> Result<()> {
-    
-    // If you want to Interact with CPI, then plz contact to me.
+pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, shares: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+
+    let token_one_accounts = (
+        &mut ctx.accounts.coin_mint.clone(),
+        &mut *ctx.accounts.pool_token_account_one,
+        &mut *ctx.accounts.user_token_account_one,
+    );
+
+    let token_two_accounts = (
+        &mut ctx.accounts.coin_mint.clone(),
+        &mut ctx.accounts.global_account.to_account_info(),
+        &mut ctx.accounts.user.to_account_info().clone(),
+    );
+
+    pool.remove_liquidity(
+        token_one_accounts,
+        token_two_accounts,
+        shares,
+        &mut *ctx.accounts.liquidity_provider_account,
+        *ctx.bumps.get("global_account").unwrap(),
+        &ctx.accounts.user,
+        &ctx.accounts.token_program,
+        &ctx.accounts.system_program,
+    )?;
 
     Ok(())
 }
@@ -34,6 +48,32 @@ pub struct RemoveLiquidity<'info> {
         bump,
     )]
     pub global_account: AccountInfo<'info>,
-    
+
+    #[account(
+        mut,
+        seeds = [LiquidityProvider::SEED_PREFIX.as_bytes(), pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub liquidity_provider_account: Box<Account<'info, LiquidityProvider>>,
+
     pub coin_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = coin_mint,
+        associated_token::authority = global_account
+    )]
+    pub pool_token_account_one: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = coin_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account_one: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
 }
@@ -5,6 +5,7 @@ use anchor_spl::{
 };
 
 use crate::{
+    consts::{bonding_curve_program, TREASURY_SEED},
     errors::CustomError,
     state::{CurveConfiguration, LiquidityPool, LiquidityPoolAccount},
 };
@@ -28,6 +29,7 @@ pub fn swap(ctx: Context<Swap>, amount: u64, style: u64) -> Result<()> {
         &*ctx.accounts.dex_configuration_account,
         token_one_accounts,
         token_two_accounts,
+        &ctx.accounts.treasury,
         amount,
         style,
         *ctx.bumps.get("global_account").unwrap(),
@@ -63,6 +65,15 @@ pub struct Swap<'info> {
     )]
     pub global_account: AccountInfo<'info>,
 
+    /// CHECK: Bonding curve treasury, receives the swap fee in SOL either way
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump,
+        seeds::program = bonding_curve_program::ID
+    )]
+    pub treasury: AccountInfo<'info>,
+
     #[account(mut)]
     pub mint_token_one: Box<Account<'info, Mint>>,
 
@@ -30,12 +30,8 @@ pub mod pump {
         instructions::add_liquidity(ctx, amount_one, amount_two)
     }
 
-    pub fn remove_liquidity(
-        ctx: Context<RemoveLiquidity>,
-        nonce: u8,
-        init_pc_amount: u64,
-    ) -> Result<()> {
-        instructions::remove_liquidity(ctx, nonce, init_pc_amount)
+    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, shares: u64) -> Result<()> {
+        instructions::remove_liquidity(ctx, shares)
     }
 
     pub fn swap(ctx: Context<Swap>, amount: u64, style: u64) -> Result<()> {
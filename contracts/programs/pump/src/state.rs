@@ -131,8 +131,10 @@ pub trait LiquidityPoolAccount<'info> {
         ),
         shares: u64,
         liquidity_provider_account: &mut Account<'info, LiquidityProvider>,
+        bump: u8,
         authority: &Signer<'info>,
         token_program: &Program<'info, Token>,
+        system_program: &Program<'info, System>,
     ) -> Result<()>;
 
     fn swap(
@@ -148,6 +150,7 @@ pub trait LiquidityPoolAccount<'info> {
             &mut AccountInfo<'info>,
             &mut Signer<'info>,
         ),
+        treasury: &AccountInfo<'info>,
         amount: u64,
         style: u64,
         bump: u8,
@@ -323,8 +326,10 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
         ),
         shares: u64,
         liquidity_provider_account: &mut Account<'info, LiquidityProvider>,
+        bump: u8,
         _authority: &Signer<'info>,
         token_program: &Program<'info, Token>,
+        system_program: &Program<'info, System>,
     ) -> Result<()> {
         if shares <= 0 {
             return err!(CustomError::FailedToRemoveLiquidity);
@@ -367,12 +372,22 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
 
         self.update_reserves(new_reserves_one, new_reserves_two)?;
 
-        // self.transfer_token_from_pool(
-        //     token_one_accounts.1,
-        //     token_one_accounts.2,
-        //     amount_out_one,
-        //     token_program,
-        // )?;
+        self.transfer_token_from_pool(
+            token_one_accounts.1,
+            token_one_accounts.2,
+            amount_out_one,
+            token_program,
+            token_two_accounts.1,
+            bump
+        )?;
+
+        self.transfer_sol_from_pool(
+            token_two_accounts.1,
+            token_two_accounts.2,
+            amount_out_two,
+            system_program,
+            bump
+        )?;
 
         Ok(())
     }
@@ -390,6 +405,7 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
             &mut AccountInfo<'info>,
             &mut Signer<'info>,
         ),
+        treasury: &AccountInfo<'info>,
         amount: u64,
         style: u64,
         bump: u8,
@@ -424,18 +440,32 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
             convert_from_float(adjusted_amount_in_float, token_one_accounts.0.decimals);
 
         if style == 1 {
+            // Tokens in: price the full amount, then take the fee out of the
+            // SOL paid out
             let denominator_sum = self
                 .reserve_one
-                .checked_add(adjusted_amount)
+                .checked_add(amount)
                 .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
 
             let div_amt = convert_to_float(denominator_sum, token_one_accounts.0.decimals).div(
-                convert_to_float(adjusted_amount, token_one_accounts.0.decimals),
+                convert_to_float(amount, token_one_accounts.0.decimals),
             );
 
-            let amount_out_in_float = convert_to_float(self.reserve_two, 9 as u8).div(div_amt);
+            let gross_out_in_float = convert_to_float(self.reserve_two, 9 as u8).div(div_amt);
 
-            let amount_out = convert_from_float(amount_out_in_float, 9 as u8);
+            let gross_out = convert_from_float(gross_out_in_float, 9 as u8);
+
+            let amount_out = convert_from_float(
+                gross_out_in_float
+                    .div(100_f64)
+                    .mul(100_f64.sub(_bonding_configuration_account.fees)),
+                9 as u8,
+            );
+
+            // The fee on SOL paid out goes to the treasury, not the user
+            let protocol_fee = gross_out
+                .checked_sub(amount_out)
+                .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
 
             let new_reserves_one = self
                 .reserve_one
@@ -444,7 +474,7 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
 
             let new_reserves_two = self
                 .reserve_two
-                .checked_sub(amount_out)
+                .checked_sub(gross_out)
                 .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
 
             self.update_reserves(new_reserves_one, new_reserves_two)?;
@@ -456,14 +486,22 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
                 authority,
                 token_program,
             )?;
-           
+
             self.transfer_sol_from_pool(
-                token_two_accounts.2,
                 token_two_accounts.1,
+                &token_two_accounts.2.to_account_info(),
                 amount_out,
                 system_program,
                 bump
             )?;
+
+            self.transfer_sol_from_pool(
+                token_two_accounts.1,
+                treasury,
+                protocol_fee,
+                system_program,
+                bump
+            )?;
         } else {
             let denominator_sum = self
                 .reserve_two
@@ -483,9 +521,14 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
                 .checked_sub(amount_out)
                 .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
 
+            // The fee on SOL paid in goes to the treasury, not the pool
+            let protocol_fee = amount
+                .checked_sub(adjusted_amount)
+                .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
+
             let new_reserves_two = self
                 .reserve_two
-                .checked_add(amount)
+                .checked_add(adjusted_amount)
                 .ok_or(CustomError::OverflowOrUnderflowOccurred)?;
             
             self.update_reserves(new_reserves_one, new_reserves_two)?;
@@ -503,7 +546,14 @@ impl<'info> LiquidityPoolAccount<'info> for Account<'info, LiquidityPool> {
             self.transfer_sol_to_pool(
                 token_two_accounts.2,
                 token_two_accounts.1,
-                amount,
+                adjusted_amount,
+                system_program,
+            )?;
+
+            self.transfer_sol_to_pool(
+                token_two_accounts.2,
+                treasury,
+                protocol_fee,
                 system_program,
             )?;
         }
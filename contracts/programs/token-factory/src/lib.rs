@@ -1,8 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
-};
+use anchor_spl::token::{Mint, Token};
+use bonding_curve::curve::{CurveKind, CurveParams};
+use bonding_curve::program::BondingCurve as BondingCurveProgram;
+use bonding_curve::{PlatformConfig, Treasury};
 use mpl_token_metadata::{
     instructions::{CreateMetadataAccountV3, CreateMetadataAccountV3InstructionArgs},
     types::DataV2,
@@ -14,20 +14,72 @@ declare_id!("8SJXJETfrrb8YHCr1FJPDVXy6F3pV2aQ6N8RWB2N3e5h");
 pub mod token_factory {
     use super::*;
 
-    /// Create a new SPL token with metadata and bonding curve
+    /// Create the singleton factory config (platform admin only)
+    pub fn initialize_factory_config(
+        ctx: Context<InitializeFactoryConfig>,
+        creation_fee: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.factory_config;
+        config.creation_fee = creation_fee;
+        config.bump = ctx.bumps.factory_config;
+        
+        emit!(CreationFeeUpdated { creation_fee });
+        
+        Ok(())
+    }
+
+    /// Change the SOL fee charged by `create_token` (platform admin only)
+    pub fn set_creation_fee(ctx: Context<UpdateFactoryConfig>, creation_fee: u64) -> Result<()> {
+        ctx.accounts.factory_config.creation_fee = creation_fee;
+        
+        emit!(CreationFeeUpdated { creation_fee });
+        
+        Ok(())
+    }
+
+    /// Allowlist a partner wallet, whose launches skip the creation fee
+    /// (platform admin only)
+    pub fn add_partner(ctx: Context<AddPartner>, wallet: Pubkey) -> Result<()> {
+        let partner = &mut ctx.accounts.partner;
+        partner.wallet = wallet;
+        partner.bump = ctx.bumps.partner;
+        
+        emit!(PartnerAdded { wallet });
+        
+        Ok(())
+    }
+
+    /// Remove a partner wallet from the allowlist (platform admin only)
+    pub fn remove_partner(ctx: Context<RemovePartner>) -> Result<()> {
+        emit!(PartnerRemoved {
+            wallet: ctx.accounts.partner.wallet,
+        });
+        
+        Ok(())
+    }
+
+    /// Create a new SPL token with metadata and bonding curve. The curve's
+    /// `initialize_curve` charges the factory's creation fee into the
+    /// treasury unless the creator is an allowlisted partner.
     pub fn create_token(
         ctx: Context<CreateToken>,
         name: String,
         symbol: String,
         uri: String,
-        base_price: u64,
-        max_supply: u64,
+        params: CurveParams,
     ) -> Result<()> {
         require!(name.len() <= 32, ErrorCode::NameTooLong);
         require!(symbol.len() <= 10, ErrorCode::SymbolTooLong);
         require!(uri.len() <= 200, ErrorCode::UriTooLong);
-        require!(base_price > 0, ErrorCode::InvalidBasePrice);
-        require!(max_supply > 0, ErrorCode::InvalidMaxSupply);
+        require!(params.base_price > 0, ErrorCode::InvalidBasePrice);
+        require!(params.max_supply > 0, ErrorCode::InvalidMaxSupply);
+        let (base_price, max_supply) = (params.base_price, params.max_supply);
+        
+        let creation_fee = if ctx.accounts.partner.is_some() {
+            0
+        } else {
+            ctx.accounts.factory_config.creation_fee
+        };
         
         // Create token metadata
         let metadata_infos = vec![
@@ -79,13 +131,18 @@ pub mod token_factory {
             bonding_curve: ctx.accounts.bonding_curve.to_account_info(),
             token_mint: ctx.accounts.mint.to_account_info(),
             creator: ctx.accounts.creator.to_account_info(),
-            fee_collector: ctx.accounts.fee_collector.to_account_info(),
+            platform_config: ctx.accounts.platform_config.to_account_info(),
             sol_vault: ctx.accounts.sol_vault.to_account_info(),
+            creator_fee_vault: ctx.accounts.creator_fee_vault.to_account_info(),
+            curve_config: None,
+            treasury: ctx.accounts.treasury.to_account_info(),
+            factory_config: ctx.accounts.factory_config.to_account_info(),
+            partner: ctx.accounts.partner.as_ref().map(|partner| partner.to_account_info()),
             system_program: ctx.accounts.system_program.to_account_info(),
         };
         
         let cpi_ctx = CpiContext::new(bonding_curve_program, cpi_accounts);
-        bonding_curve::cpi::initialize_curve(cpi_ctx, base_price, max_supply)?;
+        bonding_curve::cpi::initialize_curve(cpi_ctx, CurveKind::Quadratic, params)?;
         
         emit!(TokenCreated {
             token_mint: ctx.accounts.mint.key(),
@@ -95,6 +152,7 @@ pub mod token_factory {
             uri,
             base_price,
             max_supply,
+            creation_fee,
         });
         
         Ok(())
@@ -156,24 +214,153 @@ pub struct CreateToken<'info> {
     pub creator: Signer<'info>,
     
     /// CHECK: Bonding curve account (will be initialized via CPI)
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump,
+        seeds::program = bonding_curve_program.key()
+    )]
     pub bonding_curve: AccountInfo<'info>,
     
     /// CHECK: SOL vault PDA for bonding curve
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"sol_vault", mint.key().as_ref()],
+        bump,
+        seeds::program = bonding_curve_program.key()
+    )]
     pub sol_vault: AccountInfo<'info>,
     
-    /// CHECK: Fee collector account
-    pub fee_collector: AccountInfo<'info>,
+    /// CHECK: Creator fee vault PDA for bonding curve
+    #[account(
+        mut,
+        seeds = [b"creator_fee_vault", mint.key().as_ref()],
+        bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub creator_fee_vault: AccountInfo<'info>,
     
-    /// CHECK: Bonding curve program
-    pub bonding_curve_program: AccountInfo<'info>,
+    /// Bonding curve platform config, supplies the curve's fee collector
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    #[account(seeds = [b"factory_config"], bump = factory_config.bump)]
+    pub factory_config: Account<'info, FactoryConfig>,
+    
+    /// The creator's partner entry; waives the creation fee
+    #[account(seeds = [b"partner", creator.key().as_ref()], bump = partner.bump)]
+    pub partner: Option<Account<'info, Partner>>,
+    
+    /// Bonding curve treasury, receives the creation fee from `initialize_curve`
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury.bump,
+        seeds::program = bonding_curve_program.key()
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    pub bonding_curve_program: Program<'info, BondingCurveProgram>,
     
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct InitializeFactoryConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FactoryConfig::INIT_SPACE,
+        seeds = [b"factory_config"],
+        bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+    
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve::ID
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Platform admin
+    #[account(mut, address = platform_config.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFactoryConfig<'info> {
+    #[account(mut, seeds = [b"factory_config"], bump = factory_config.bump)]
+    pub factory_config: Account<'info, FactoryConfig>,
+    
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve::ID
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Platform admin
+    #[account(address = platform_config.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AddPartner<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Partner::INIT_SPACE,
+        seeds = [b"partner", wallet.as_ref()],
+        bump
+    )]
+    pub partner: Account<'info, Partner>,
+    
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve::ID
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Platform admin
+    #[account(mut, address = platform_config.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePartner<'info> {
+    #[account(
+        mut,
+        seeds = [b"partner", partner.wallet.as_ref()],
+        bump = partner.bump,
+        close = admin
+    )]
+    pub partner: Account<'info, Partner>,
+    
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = bonding_curve::ID
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    
+    /// Platform admin
+    #[account(mut, address = platform_config.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateMetadata<'info> {
     pub mint: Account<'info, Mint>,
@@ -188,6 +375,26 @@ pub struct UpdateMetadata<'info> {
     pub system_program: Program<'info, System>,
 }
 
+// State
+/// Singleton factory parameters (seeds: `["factory_config"]`)
+#[account]
+#[derive(InitSpace)]
+pub struct FactoryConfig {
+    /// Lamports the bonding curve's `initialize_curve` charges into the
+    /// treasury
+    pub creation_fee: u64,
+    pub bump: u8,
+}
+
+/// Allowlisted partner wallet (seeds: `["partner", wallet]`); its launches
+/// pay no creation fee
+#[account]
+#[derive(InitSpace)]
+pub struct Partner {
+    pub wallet: Pubkey,
+    pub bump: u8,
+}
+
 // Events
 #[event]
 pub struct TokenCreated {
@@ -198,6 +405,23 @@ pub struct TokenCreated {
     pub uri: String,
     pub base_price: u64,
     pub max_supply: u64,
+    /// Lamports charged into the treasury, 0 for partners
+    pub creation_fee: u64,
+}
+
+#[event]
+pub struct CreationFeeUpdated {
+    pub creation_fee: u64,
+}
+
+#[event]
+pub struct PartnerAdded {
+    pub wallet: Pubkey,
+}
+
+#[event]
+pub struct PartnerRemoved {
+    pub wallet: Pubkey,
 }
 
 #[event]
@@ -219,36 +443,7 @@ pub enum ErrorCode {
     InvalidBasePrice,
     #[msg("Invalid max supply")]
     InvalidMaxSupply,
+    #[msg("Unauthorized")]
+    Unauthorized,
 }
 
-// External program CPI interfaces
-pub mod bonding_curve {
-    use super::*;
-    
-    pub mod cpi {
-        use super::*;
-        
-        pub mod accounts {
-            use super::*;
-            
-            pub struct InitializeCurve<'info> {
-                pub bonding_curve: AccountInfo<'info>,
-                pub token_mint: AccountInfo<'info>,
-                pub creator: AccountInfo<'info>,
-                pub fee_collector: AccountInfo<'info>,
-                pub sol_vault: AccountInfo<'info>,
-                pub system_program: AccountInfo<'info>,
-            }
-        }
-        
-        pub fn initialize_curve(
-            ctx: CpiContext<accounts::InitializeCurve>,
-            base_price: u64,
-            max_supply: u64,
-        ) -> Result<()> {
-            // This would normally use anchor_lang::solana_program::program::invoke_signed
-            // For now, simplified version
-            Ok(())
-        }
-    }
-}
@@ -1,14 +1,244 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { BondingCurve } from "../target/types/bonding_curve";
+import { TokenFactory } from "../target/types/token_factory";
 import { PublicKey, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { 
   TOKEN_PROGRAM_ID,
-  createMint,
   getOrCreateAssociatedTokenAccount,
-  mintTo,
 } from "@solana/spl-token";
 import { expect } from "chai";
+import {
+  allowlistTree,
+  bondingCurveAddress,
+  CONFIG_PARAMS,
+  CREATION_FEE,
+  createCurveMint,
+  creatorFeeVaultAddress,
+  curveParams,
+  ensureFactoryConfig,
+  ensurePlatformConfig,
+  CREATOR_FEE_SHARE_BPS,
+  FEE_BPS,
+  initializeConfigAccounts,
+  launchFixture,
+  LaunchCurve,
+  LaunchFixture,
+  NO_CONFIG_UPDATE,
+  platformConfigAddress,
+  referralAddress,
+  treasuryAddress,
+} from "./helpers";
+
+const TOKEN_PRECISION = 1_000_000_000n;
+
+// Floor cube root, mirrors math::icbrt
+function icbrt(n: bigint): bigint {
+  if (n === 0n) return 0n;
+  let x = 1n << BigInt(Math.ceil(n.toString(2).length / 3));
+  while (true) {
+    const y = (2n * x + n / (x * x)) / 3n;
+    if (y >= x) return x;
+    x = y;
+  }
+}
+
+// Tokens minted for `sol` lamports (after fee) on the quadratic curve
+function quadraticBuyTokens(
+  supply: bigint,
+  maxSupply: bigint,
+  basePrice: bigint,
+  sol: bigint
+): bigint {
+  const from = maxSupply + supply;
+  const denominator = 3n * TOKEN_PRECISION * maxSupply * maxSupply;
+  return icbrt(from ** 3n + (sol * denominator) / basePrice) - from;
+}
+
+// Floor square root, mirrors math::isqrt
+function isqrt(n: bigint): bigint {
+  if (n === 0n) return 0n;
+  let x = 1n << BigInt(Math.ceil(n.toString(2).length / 2));
+  while (true) {
+    const y = (x + n / x) >> 1n;
+    if (y >= x) return x;
+    x = y;
+  }
+}
+
+const ONE_Q64 = 1n << 64n;
+const LN2_Q64 = 12_786_308_645_202_655_659n;
+
+// 2^x in Q64.64, mirrors math::exp2_q64
+function exp2Q64(x: bigint): bigint {
+  const y = ((x & (ONE_Q64 - 1n)) * LN2_Q64) >> 64n;
+  let sum = ONE_Q64;
+  let term = ONE_Q64;
+  for (let k = 1n; term > 0n; k++) {
+    term = ((term * y) >> 64n) / k;
+    sum += term;
+  }
+  return sum << (x >> 64n);
+}
+
+// log2(x) in Q64.64, mirrors math::log2_q64
+function log2Q64(x: bigint): bigint {
+  const whole = BigInt(x.toString(2).length - 65);
+  let result = whole << 64n;
+  let y = x >> whole;
+  for (let bit = 63n; bit >= 0n; bit--) {
+    y = (y * y) >> 64n;
+    if (y >= 2n * ONE_Q64) {
+      y >>= 1n;
+      result |= 1n << bit;
+    }
+  }
+  return result;
+}
+
+function ceilDiv(a: bigint, b: bigint): bigint {
+  return (a + b - 1n) / b;
+}
+
+// The parts of a BondingCurve account the curve formulas read
+interface CurveState {
+  supply: bigint;
+  virtualSol: bigint;
+  virtualTokens: bigint;
+}
+
+// Off-chain mirrors of the curve formulas in curve.rs
+interface CurveMath {
+  buyTokensOut(curve: CurveState, sol: bigint): bigint;
+  sellSolOut(curve: CurveState, tokens: bigint): bigint;
+}
+
+// order 2 = linear, order 3 = quadratic
+function polynomialMath(order: bigint, maxSupply: bigint, basePrice: bigint): CurveMath {
+  const denominator = order * TOKEN_PRECISION * maxSupply ** (order - 1n);
+  const root = order === 2n ? isqrt : icbrt;
+  return {
+    buyTokensOut: ({ supply }, sol) => {
+      const from = maxSupply + supply;
+      return root(from ** order + (sol * denominator) / basePrice) - from;
+    },
+    sellSolOut: ({ supply }, tokens) => {
+      const from = maxSupply + supply;
+      return ((from ** order - (from - tokens) ** order) * basePrice) / denominator;
+    },
+  };
+}
+
+function exponentialMath(doublingSupply: bigint, basePrice: bigint): CurveMath {
+  const growth = (supply: bigint) => exp2Q64((supply << 64n) / doublingSupply);
+  const denominator = TOKEN_PRECISION * LN2_Q64;
+  const area = (from: bigint, to: bigint) => (growth(to) - growth(from)) * doublingSupply * basePrice;
+  return {
+    buyTokensOut: ({ supply }, sol) => {
+      const target = growth(supply) + (sol * denominator) / (basePrice * doublingSupply);
+      let tokens = ((log2Q64(target) * doublingSupply) >> 64n) - supply;
+      // Step back while the rounded-up cost tops the budget
+      while (tokens > 0n && ceilDiv(area(supply, supply + tokens), denominator) > sol) tokens--;
+      return tokens;
+    },
+    sellSolOut: ({ supply }, tokens) => area(supply - tokens, supply) / denominator,
+  };
+}
+
+const constantProductMath: CurveMath = {
+  buyTokensOut: ({ virtualSol, virtualTokens }, sol) => (virtualTokens * sol) / (virtualSol + sol),
+  sellSolOut: ({ virtualSol, virtualTokens }, tokens) =>
+    (virtualSol * tokens) / (virtualTokens + tokens),
+};
+
+// A piecewise curve config as (lower, upper, liquidity) ranges
+type PiecewiseRange = [bigint, bigint, bigint];
+
+function piecewiseRanges(sqrtStartPrice: bigint, segments: [bigint, bigint][]): PiecewiseRange[] {
+  return segments.map(([sqrtPrice, liquidity], i) => [
+    i === 0 ? sqrtStartPrice : segments[i - 1][0],
+    sqrtPrice,
+    liquidity,
+  ]);
+}
+
+// Tokens between two sqrt prices, mirrors piecewise::delta_base
+function deltaBase(lower: bigint, upper: bigint, liquidity: bigint, roundUp: boolean): bigint {
+  const numerator = liquidity * (upper - lower);
+  return roundUp ? ceilDiv(numerator, lower * upper) : numerator / (lower * upper);
+}
+
+// Lamports between two sqrt prices, mirrors piecewise::delta_quote
+function deltaQuote(lower: bigint, upper: bigint, liquidity: bigint, roundUp: boolean): bigint {
+  const product = liquidity * (upper - lower);
+  return roundUp ? ceilDiv(product, 1n << 128n) : product >> 128n;
+}
+
+// Walks a buy of `sol` lamports up the segments, mirrors piecewise::buy_tokens_out
+function piecewiseBuy(ranges: PiecewiseRange[], sqrtPrice: bigint, sol: bigint) {
+  let tokens = 0n;
+  for (const [, upper, liquidity] of ranges) {
+    if (sol === 0n) break;
+    if (upper <= sqrtPrice) continue;
+
+    const segmentSol = deltaQuote(sqrtPrice, upper, liquidity, true);
+    const next = sol < segmentSol ? sqrtPrice + (sol << 128n) / liquidity : upper;
+    tokens += deltaBase(sqrtPrice, next, liquidity, false);
+    sol = sol < segmentSol ? 0n : sol - segmentSol;
+    sqrtPrice = next;
+  }
+  return { tokens, sqrtPrice };
+}
+
+// Walks a sell of `tokens` down the segments, mirrors piecewise::sell_sol_out
+function piecewiseSell(ranges: PiecewiseRange[], sqrtPrice: bigint, tokens: bigint) {
+  let sol = 0n;
+  for (const [lower, , liquidity] of [...ranges].reverse()) {
+    if (tokens === 0n) break;
+    if (lower >= sqrtPrice) continue;
+
+    const segmentTokens = deltaBase(lower, sqrtPrice, liquidity, true);
+    const next =
+      tokens < segmentTokens
+        ? ceilDiv(liquidity * sqrtPrice, liquidity + tokens * sqrtPrice)
+        : lower;
+    sol += deltaQuote(next, sqrtPrice, liquidity, false);
+    tokens = tokens < segmentTokens ? 0n : tokens - segmentTokens;
+    sqrtPrice = next;
+  }
+  return { sol, sqrtPrice };
+}
+
+describe("Platform config", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.BondingCurve as Program<BondingCurve>;
+
+  it("Rejects initialize_config from a wallet that isn't the upgrade authority", async function () {
+    // Runs before any suite creates the singleton; once it exists `init`
+    // fails first and the authority check can't be reached
+    const config = await program.account.platformConfig.fetchNullable(
+      platformConfigAddress(program.programId)
+    );
+    if (config !== null) this.skip();
+
+    const squatter = Keypair.generate();
+    const sig = await provider.connection.requestAirdrop(squatter.publicKey, LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(sig);
+
+    try {
+      await program.methods
+        .initializeConfig(CONFIG_PARAMS)
+        .accounts(initializeConfigAccounts(program, squatter.publicKey))
+        .signers([squatter])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+  });
+});
 
 describe("Bonding Curve", () => {
   const provider = anchor.AnchorProvider.env();
@@ -19,16 +249,23 @@ describe("Bonding Curve", () => {
   let tokenMint: PublicKey;
   let bondingCurve: PublicKey;
   let solVault: PublicKey;
+  let creatorFeeVault: PublicKey;
+  let referral: PublicKey;
+  let platformConfig: PublicKey;
+  let feeCollector: PublicKey;
+  let factoryConfig: PublicKey;
   let buyerTokenAccount: PublicKey;
   let sellerTokenAccount: PublicKey;
   
   const creator = Keypair.generate();
   const buyer = Keypair.generate();
   const seller = Keypair.generate();
-  const feeCollector = Keypair.generate();
+  
+  // Referral codes are global, so make this run's unique
+  const referralCode = `s${Date.now().toString(36)}`;
   
   const BASE_PRICE = new anchor.BN(100_000); // 0.0001 SOL
-  const MAX_SUPPLY = new anchor.BN(1_000_000_000); // 1B tokens
+  const MAX_SUPPLY = new anchor.BN("1000000000000000000"); // 1B tokens (9 decimals)
 
   before(async () => {
     // Airdrop SOL to test accounts
@@ -48,15 +285,15 @@ describe("Bonding Curve", () => {
     // Wait for airdrops to confirm
     await new Promise(resolve => setTimeout(resolve, 1000));
 
-    // Create mint
-    tokenMint = await createMint(
-      provider.connection,
-      creator,
-      creator.publicKey,
-      null,
-      9 // decimals
+    ({ platformConfig, feeCollector } = await ensurePlatformConfig(program));
+    factoryConfig = await ensureFactoryConfig(
+      anchor.workspace.TokenFactory as Program<TokenFactory>,
+      platformConfig
     );
 
+    // Create mint
+    tokenMint = await createCurveMint(program, creator);
+
     // Derive PDAs
     [bondingCurve] = PublicKey.findProgramAddressSync(
       [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
@@ -67,17 +304,24 @@ describe("Bonding Curve", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       program.programId
     );
+
+    creatorFeeVault = creatorFeeVaultAddress(program.programId, tokenMint);
   });
 
   it("Initializes bonding curve", async () => {
+    const treasuryBefore = await provider.connection.getBalance(feeCollector);
+
     const tx = await program.methods
-      .initializeCurve(BASE_PRICE, MAX_SUPPLY)
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY))
       .accounts({
         bondingCurve,
+        platformConfig,
         tokenMint,
         creator: creator.publicKey,
-        feeCollector: feeCollector.publicKey,
         solVault,
+        creatorFeeVault,
+        treasury: feeCollector,
+        factoryConfig,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([creator])
@@ -88,10 +332,560 @@ describe("Bonding Curve", () => {
     expect(curveAccount.tokenMint.toString()).to.equal(tokenMint.toString());
     expect(curveAccount.creator.toString()).to.equal(creator.publicKey.toString());
     expect(curveAccount.basePrice.toNumber()).to.equal(BASE_PRICE.toNumber());
-    expect(curveAccount.maxSupply.toNumber()).to.equal(MAX_SUPPLY.toNumber());
+    expect(curveAccount.maxSupply.toString()).to.equal(MAX_SUPPLY.toString());
     expect(curveAccount.tokenSupply.toNumber()).to.equal(0);
     expect(curveAccount.solReserves.toNumber()).to.equal(0);
     expect(curveAccount.graduated).to.be.false;
+
+    // Creating the curve directly still pays the token factory's creation fee
+    const treasuryAfter = await provider.connection.getBalance(feeCollector);
+    expect(treasuryAfter - treasuryBefore).to.equal(CREATION_FEE.toNumber());
+  });
+
+  it("Rejects a creation fee config that isn't the token factory's", async () => {
+    const mint = await createCurveMint(program, creator);
+
+    try {
+      await program.methods
+        .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY))
+        .accounts({
+          bondingCurve: bondingCurveAddress(program.programId, mint),
+          platformConfig,
+          tokenMint: mint,
+          creator: creator.publicKey,
+          solVault: PublicKey.findProgramAddressSync(
+            [Buffer.from("sol_vault"), mint.toBuffer()],
+            program.programId
+          )[0],
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, mint),
+          treasury: feeCollector,
+          factoryConfig: platformConfig,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Sends the curve's platform fees to the treasury", async () => {
+    const curveAccount = await program.account.bondingCurve.fetch(bondingCurve);
+    expect(curveAccount.feeCollector.toString()).to.equal(feeCollector.toString());
+    expect(feeCollector.toString()).to.equal(treasuryAddress(program.programId).toString());
+  });
+
+  it("Records the platform's post-graduation fee tier on the curve", async () => {
+    const config = await program.account.platformConfig.fetch(platformConfig);
+    const curveAccount = await program.account.bondingCurve.fetch(bondingCurve);
+    expect(curveAccount.postGraduationFeeBps).to.equal(config.postGraduationFeeBps);
+  });
+
+  it("Rejects config updates from a non-admin", async () => {
+    try {
+      await program.methods
+        .updateConfig({ ...NO_CONFIG_UPDATE, paused: true })
+        .accounts({
+          platformConfig,
+          admin: creator.publicKey,
+        })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+  });
+
+  it("Rejects a base price outside the platform bounds", async () => {
+    const config = await program.account.platformConfig.fetch(platformConfig);
+    const outMint = await createCurveMint(program, creator);
+    const [outCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), outMint.toBuffer()],
+      program.programId
+    );
+    const [outVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), outMint.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializeCurve({ quadratic: {} }, curveParams(config.maxBasePrice.addn(1), MAX_SUPPLY))
+        .accounts({
+          bondingCurve: outCurve,
+          platformConfig,
+          tokenMint: outMint,
+          creator: creator.publicKey,
+          solVault: outVault,
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, outMint),
+          treasury: feeCollector,
+          factoryConfig,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidBasePrice");
+    }
+  });
+
+  it("Rejects a curve fee above the platform maximum", async () => {
+    const config = await program.account.platformConfig.fetch(platformConfig);
+    const feeMint = await createCurveMint(program, creator);
+    const [feeCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), feeMint.toBuffer()],
+      program.programId
+    );
+    const [feeVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), feeMint.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY, { sellFeeBps: config.maxFeeBps + 1 }))
+        .accounts({
+          bondingCurve: feeCurve,
+          platformConfig,
+          tokenMint: feeMint,
+          creator: creator.publicKey,
+          solVault: feeVault,
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, feeMint),
+          treasury: feeCollector,
+          factoryConfig,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("FeeTooHigh");
+    }
+  });
+
+  it("Charges each curve its own buy and sell fees", async () => {
+    const buyFeeBps = 50; // 0.5%
+    const sellFeeBps = 150; // 1.5%
+    const feeMint = await createCurveMint(program, creator);
+    const [feeCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), feeMint.toBuffer()],
+      program.programId
+    );
+    const [feeVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), feeMint.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY, { buyFeeBps, sellFeeBps }))
+      .accounts({
+        bondingCurve: feeCurve,
+        platformConfig,
+        tokenMint: feeMint,
+        creator: creator.publicKey,
+        solVault: feeVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, feeMint),
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const curveAccount = await program.account.bondingCurve.fetch(feeCurve);
+    expect(curveAccount.buyFeeBps).to.equal(buyFeeBps);
+    expect(curveAccount.sellFeeBps).to.equal(sellFeeBps);
+
+    const feeBuyerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      buyer,
+      feeMint,
+      buyer.publicKey
+    );
+
+    const solAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+    const buyQuote = await program.methods
+      .quoteBuy(solAmount, false)
+      .accounts({ bondingCurve: feeCurve, platformConfig })
+      .view();
+    expect(buyQuote.fee.toNumber()).to.equal((solAmount.toNumber() * buyFeeBps) / 10_000);
+
+    await program.methods
+      .buy(solAmount, buyQuote.amountOut, false, null)
+      .accounts({
+        bondingCurve: feeCurve,
+        platformConfig,
+        tokenMint: feeMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount: feeBuyerTokenAccount.address,
+        solVault: feeVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, feeMint),
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const sellQuote = await program.methods
+      .quoteSell(buyQuote.amountOut)
+      .accounts({ bondingCurve: feeCurve, platformConfig })
+      .view();
+    const gross = BigInt(sellQuote.amountOut.add(sellQuote.fee).toString());
+    const expectedFee = (gross * BigInt(sellFeeBps) + 9_999n) / 10_000n;
+    expect(sellQuote.fee.toString()).to.equal(expectedFee.toString());
+  });
+
+  describe("launch fee", () => {
+    let fixture: LaunchFixture;
+
+    before(async () => {
+      fixture = await launchFixture();
+    });
+
+    const SOL_AMOUNT = 0.1 * LAMPORTS_PER_SOL;
+
+    // Buys SOL_AMOUNT and checks it was charged the buy fee plus
+    // `surchargeBps`, with the surcharge on top of its usual fee share
+    // going to the creator
+    async function expectBuyWithSurcharge(curve: LaunchCurve, surchargeBps: number) {
+      const { buyer } = fixture;
+      const buyerBefore = await fixture.balance(buyer.publicKey);
+      const vaultBefore = await fixture.balance(curve.solVault);
+      const creatorBefore = await fixture.balance(curve.creatorFeeVault);
+
+      await fixture.buy(curve, buyer, new anchor.BN(SOL_AMOUNT));
+
+      const paid = buyerBefore - (await fixture.balance(buyer.publicKey));
+      const fee = paid - ((await fixture.balance(curve.solVault)) - vaultBefore);
+      const creatorReceived = (await fixture.balance(curve.creatorFeeVault)) - creatorBefore;
+
+      const feeBps = FEE_BPS + surchargeBps;
+      expect(paid).to.equal(SOL_AMOUNT);
+      expect(fee).to.equal(Math.ceil((SOL_AMOUNT * feeBps) / 10_000));
+
+      const launchFee = Math.floor((fee * surchargeBps) / feeBps);
+      const creatorShare = Math.floor(((fee - launchFee) * CREATOR_FEE_SHARE_BPS) / 10_000);
+      expect(creatorReceived).to.equal(launchFee + creatorShare);
+    }
+
+    it("Rejects a launch fee that does not start above the buy fee", async () => {
+      try {
+        await fixture.createCurve(
+          curveParams(BASE_PRICE, MAX_SUPPLY, {
+            launchFee: {
+              startFeeBps: FEE_BPS,
+              durationSlots: new anchor.BN(100),
+              decay: { linear: {} },
+              recipient: { creator: {} },
+            },
+          })
+        );
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("InvalidLaunchFee");
+      }
+    });
+
+    it("Routes a linearly decaying launch surcharge to the creator", async () => {
+      const startFeeBps = 5_000;
+      const durationSlots = 1_000;
+      const curve = await fixture.createCurve(
+        curveParams(BASE_PRICE, MAX_SUPPLY, {
+          launchFee: {
+            startFeeBps,
+            durationSlots: new anchor.BN(durationSlots),
+            decay: { linear: {} },
+            recipient: { creator: {} },
+          },
+        })
+      );
+
+      const curveAccount = await fixture.program.account.bondingCurve.fetch(curve.bondingCurve);
+      const { slot } = await fixture.context.banksClient.getClock();
+      expect(curveAccount.launchFee.startFeeBps).to.equal(startFeeBps);
+      expect(curveAccount.launchSlot.toString()).to.equal(slot.toString());
+
+      // Full surcharge in the launch slot, half of it halfway through the
+      // window and none once the window has passed
+      await expectBuyWithSurcharge(curve, startFeeBps - FEE_BPS);
+      await fixture.warpSlots(durationSlots / 2);
+      await expectBuyWithSurcharge(curve, (startFeeBps - FEE_BPS) / 2);
+      await fixture.warpSlots(durationSlots / 2);
+      await expectBuyWithSurcharge(curve, 0);
+    });
+
+    it("Falls back to the normal buy fee once the launch window ends", async () => {
+      const curve = await fixture.createCurve(
+        curveParams(BASE_PRICE, MAX_SUPPLY, {
+          launchFee: {
+            startFeeBps: 5_000,
+            durationSlots: new anchor.BN(2),
+            decay: { exponential: {} },
+            recipient: { treasury: {} },
+          },
+        })
+      );
+
+      await fixture.warpSlots(2);
+
+      const treasuryBefore = await fixture.balance(fixture.feeCollector);
+      await expectBuyWithSurcharge(curve, 0);
+
+      // The treasury only gets its share of the normal fee
+      const fee = Math.ceil((SOL_AMOUNT * FEE_BPS) / 10_000);
+      const creatorShare = Math.floor((fee * CREATOR_FEE_SHARE_BPS) / 10_000);
+      expect((await fixture.balance(fixture.feeCollector)) - treasuryBefore).to.equal(
+        fee - creatorShare
+      );
+    });
+  });
+
+  describe("launch limits", () => {
+    // 0.01% of a 1e15 max supply is 1e11 tokens per wallet
+    const LIMITED_MAX_SUPPLY = new anchor.BN("1000000000000000");
+    const MAX_TOKENS_PER_BUY = new anchor.BN("50000000000");
+
+    let fixture: LaunchFixture;
+
+    before(async () => {
+      fixture = await launchFixture();
+    });
+
+    // A curve with launch limits for the given window
+    function limitedCurve(durationSecs: number) {
+      return fixture.createCurve(
+        curveParams(BASE_PRICE, LIMITED_MAX_SUPPLY, {
+          launchLimits: { durationSecs, maxTokensPerBuy: MAX_TOKENS_PER_BUY, maxWalletBps: 1 },
+        })
+      );
+    }
+
+    it("Caps single buys and wallet totals during the launch window", async () => {
+      const { buyer } = fixture;
+      const curve = await limitedCurve(3_600);
+
+      try {
+        await fixture.buyExact(curve, buyer, MAX_TOKENS_PER_BUY, null, null);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("WalletPurchasesRequired");
+      }
+
+      const walletPurchases = await fixture.initializeWalletPurchases(curve, buyer);
+
+      try {
+        await fixture.buyExact(curve, buyer, MAX_TOKENS_PER_BUY.addn(1), null, walletPurchases);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("LaunchBuyTooLarge");
+      }
+
+      // Two buys at the per-buy cap fill the wallet cap exactly. The window
+      // is in seconds, so the slot between them only keeps the second buy
+      // from being a duplicate of the first.
+      await fixture.buyExact(curve, buyer, MAX_TOKENS_PER_BUY, null, walletPurchases);
+      await fixture.warpSlots(1);
+      await fixture.buyExact(curve, buyer, MAX_TOKENS_PER_BUY, null, walletPurchases);
+      const purchases = await fixture.program.account.walletPurchases.fetch(walletPurchases);
+      expect(purchases.tokensBought.toString()).to.equal(MAX_TOKENS_PER_BUY.muln(2).toString());
+      expect((await fixture.tokenBalance(curve, buyer)).toString()).to.equal(
+        MAX_TOKENS_PER_BUY.muln(2).toString()
+      );
+
+      try {
+        await fixture.buyExact(curve, buyer, new anchor.BN(1), null, walletPurchases);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("LaunchWalletCapExceeded");
+      }
+    });
+
+    it("Lifts the limits once the launch window ends", async () => {
+      const curve = await limitedCurve(60);
+
+      // Still limited in the window's last second
+      await fixture.warpSeconds(59);
+      try {
+        await fixture.buyExact(curve, fixture.buyer, MAX_TOKENS_PER_BUY.muln(3), null, null);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("LaunchBuyTooLarge");
+      }
+
+      await fixture.warpSeconds(1);
+      await fixture.buyExact(curve, fixture.buyer, MAX_TOKENS_PER_BUY.muln(3), null, null);
+      expect((await fixture.tokenBalance(curve, fixture.buyer)).toString()).to.equal(
+        MAX_TOKENS_PER_BUY.muln(3).toString()
+      );
+    });
+  });
+
+  describe("allowlist", () => {
+    const ALLOCATION = new anchor.BN("1000000000000");
+
+    let fixture: LaunchFixture;
+
+    before(async () => {
+      fixture = await launchFixture();
+    });
+
+    // A curve whose allowlist phase ends `secs` seconds after the bank's
+    // clock
+    async function allowlistCurve(merkleRoot: number[], secs: number) {
+      const { unixTimestamp } = await fixture.context.banksClient.getClock();
+      const endsAt = new anchor.BN((unixTimestamp + BigInt(secs)).toString());
+      return fixture.createCurve(
+        curveParams(BASE_PRICE, MAX_SUPPLY, { allowlist: { merkleRoot, endsAt } })
+      );
+    }
+
+    // The buyer has an allocation, the creator may buy without a cap
+    const tree = () =>
+      allowlistTree([
+        { wallet: fixture.buyer.publicKey, allocation: ALLOCATION },
+        { wallet: fixture.creator.publicKey, allocation: null },
+        { wallet: Keypair.generate().publicKey, allocation: null },
+      ]);
+
+    it("Rejects an allowlist phase that has already ended", async () => {
+      try {
+        await allowlistCurve(tree().root, -60);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("InvalidAllowlist");
+      }
+    });
+
+    it("Only lets allowlisted wallets buy, up to their allocation", async () => {
+      const { buyer, creator } = fixture;
+      const allowlist = tree();
+      const curve = await allowlistCurve(allowlist.root, 3_600);
+      const buyerProof = { allocation: ALLOCATION, proof: allowlist.proof(0) };
+
+      try {
+        await fixture.buyExact(curve, buyer, ALLOCATION, null, null);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("AllowlistProofRequired");
+      }
+
+      // Claiming a larger allocation changes the leaf
+      try {
+        await fixture.buyExact(
+          curve,
+          buyer,
+          ALLOCATION,
+          { ...buyerProof, allocation: ALLOCATION.muln(2) },
+          null
+        );
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("NotAllowlisted");
+      }
+
+      // An allocation is tracked in the buyer's purchases account
+      try {
+        await fixture.buyExact(curve, buyer, ALLOCATION, buyerProof, null);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("WalletPurchasesRequired");
+      }
+
+      const walletPurchases = await fixture.initializeWalletPurchases(curve, buyer);
+
+      await fixture.buyExact(curve, buyer, ALLOCATION, buyerProof, walletPurchases);
+      expect((await fixture.tokenBalance(curve, buyer)).toString()).to.equal(
+        ALLOCATION.toString()
+      );
+      try {
+        await fixture.buyExact(curve, buyer, new anchor.BN(1), buyerProof, walletPurchases);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("AllowlistAllocationExceeded");
+      }
+
+      // Without an allocation there is nothing to track
+      await fixture.buyExact(
+        curve,
+        creator,
+        ALLOCATION.muln(2),
+        { allocation: null, proof: allowlist.proof(1) },
+        null
+      );
+      expect((await fixture.tokenBalance(curve, creator)).toString()).to.equal(
+        ALLOCATION.muln(2).toString()
+      );
+    });
+
+    it("Opens trading to everyone once the phase ends", async () => {
+      const curve = await allowlistCurve(tree().root, 60);
+
+      // Still allowlisted in the phase's last second
+      await fixture.warpSeconds(59);
+      try {
+        await fixture.buyExact(curve, fixture.buyer, ALLOCATION.muln(2), null, null);
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("AllowlistProofRequired");
+      }
+
+      await fixture.warpSeconds(1);
+      await fixture.buyExact(curve, fixture.buyer, ALLOCATION.muln(2), null, null);
+      expect((await fixture.tokenBalance(curve, fixture.buyer)).toString()).to.equal(
+        ALLOCATION.muln(2).toString()
+      );
+    });
+  });
+
+  it("Blocks trading while the platform is paused", async () => {
+    const buyerTokenAccountInfo = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      buyer,
+      tokenMint,
+      buyer.publicKey
+    );
+
+    await program.methods
+      .updateConfig({ ...NO_CONFIG_UPDATE, paused: true })
+      .accounts({
+        platformConfig,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .buy(new anchor.BN(0.1 * LAMPORTS_PER_SOL), new anchor.BN(0), false, null)
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          buyer: buyer.publicKey,
+          buyerTokenAccount: buyerTokenAccountInfo.address,
+          solVault,
+          creatorFeeVault,
+          feeCollector,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("PlatformPaused");
+    } finally {
+      await program.methods
+        .updateConfig({ ...NO_CONFIG_UPDATE, paused: false })
+        .accounts({
+          platformConfig,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    }
   });
 
   it("Buys tokens", async () => {
@@ -110,14 +904,16 @@ describe("Bonding Curve", () => {
     const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
 
     const tx = await program.methods
-      .buy(solAmount, minTokensOut)
+      .buy(solAmount, minTokensOut, false, null)
       .accounts({
         bondingCurve,
+        platformConfig,
         tokenMint,
         buyer: buyer.publicKey,
         buyerTokenAccount,
         solVault,
-        feeCollector: feeCollector.publicKey,
+        creatorFeeVault,
+        feeCollector,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -141,45 +937,624 @@ describe("Bonding Curve", () => {
     expect(curveAccount.solReserves.toNumber()).to.be.greaterThan(0);
   });
 
-  it("Sells tokens", async () => {
-    const curveAccountBefore = await program.account.bondingCurve.fetch(bondingCurve);
-    const tokenBalance = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
-    
-    // Sell half of tokens
-    const tokenAmount = new anchor.BN(parseInt(tokenBalance.value.amount) / 2);
-    const minSolOut = new anchor.BN(0);
+  it("Mints exactly the closed-form integral amount", async () => {
+    const curveBefore = await program.account.bondingCurve.fetch(bondingCurve);
+    const balanceBefore = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
 
-    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+    const solAmount = new anchor.BN(0.5 * LAMPORTS_PER_SOL);
+    const solAfterFee = BigInt(solAmount.toString()) - BigInt(solAmount.toString()) / 100n;
+    const expected = quadraticBuyTokens(
+      BigInt(curveBefore.tokenSupply.toString()),
+      BigInt(MAX_SUPPLY.toString()),
+      BigInt(BASE_PRICE.toString()),
+      solAfterFee
+    );
 
-    const tx = await program.methods
-      .sell(tokenAmount, minSolOut)
+    await program.methods
+      .buy(solAmount, new anchor.BN(expected.toString()), false, null)
       .accounts({
         bondingCurve,
+        platformConfig,
         tokenMint,
-        seller: buyer.publicKey,
-        sellerTokenAccount: buyerTokenAccount,
+        buyer: buyer.publicKey,
+        buyerTokenAccount,
         solVault,
-        feeCollector: feeCollector.publicKey,
+        creatorFeeVault,
+        feeCollector,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([buyer])
       .rpc();
 
-    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
-    const tokenBalanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
-    const curveAccountAfter = await program.account.bondingCurve.fetch(bondingCurve);
+    const balanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const received = BigInt(balanceAfter.value.amount) - BigInt(balanceBefore.value.amount);
+    expect(received.toString()).to.equal(expected.toString());
+  });
 
-    // Buyer should have received SOL
-    expect(buyerBalanceAfter).to.be.greaterThan(buyerBalanceBefore);
-    
-    // Token balance should have decreased
-    expect(parseInt(tokenBalanceAfter.value.amount)).to.be.lessThan(parseInt(tokenBalance.value.amount));
-    
-    // Curve supply should have decreased
+  it("Buys an exact token amount", async () => {
+    const balanceBefore = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    const tokenAmount = new anchor.BN("1000000000000"); // 1,000 tokens
+    const maxSolIn = new anchor.BN(1 * LAMPORTS_PER_SOL);
+
+    await program.methods
+      .buyExactOut(tokenAmount, maxSolIn, null)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const balanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    const received = BigInt(balanceAfter.value.amount) - BigInt(balanceBefore.value.amount);
+
+    expect(received.toString()).to.equal(tokenAmount.toString());
+    expect(buyerBalanceBefore - buyerBalanceAfter).to.be.lessThanOrEqual(
+      maxSolIn.toNumber() + 10_000 // tx fee
+    );
+  });
+
+  it("Rejects an exact-out buy above max_sol_in", async () => {
+    try {
+      await program.methods
+        .buyExactOut(new anchor.BN("1000000000000"), new anchor.BN(1), null)
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          buyer: buyer.publicKey,
+          buyerTokenAccount,
+          solVault,
+          creatorFeeVault,
+          feeCollector,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("SlippageExceeded");
+    }
+  });
+
+  it("Sells tokens", async () => {
+    const curveAccountBefore = await program.account.bondingCurve.fetch(bondingCurve);
+    const tokenBalance = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    
+    // Sell half of tokens
+    const tokenAmount = new anchor.BN(parseInt(tokenBalance.value.amount) / 2);
+    const minSolOut = new anchor.BN(0);
+
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    const tx = await program.methods
+      .sell(tokenAmount, minSolOut)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        seller: buyer.publicKey,
+        sellerTokenAccount: buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    const tokenBalanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const curveAccountAfter = await program.account.bondingCurve.fetch(bondingCurve);
+
+    // Buyer should have received SOL
+    expect(buyerBalanceAfter).to.be.greaterThan(buyerBalanceBefore);
+    
+    // Token balance should have decreased
+    expect(parseInt(tokenBalanceAfter.value.amount)).to.be.lessThan(parseInt(tokenBalance.value.amount));
+    
+    // Curve supply should have decreased
     expect(curveAccountAfter.tokenSupply.toNumber()).to.be.lessThan(curveAccountBefore.tokenSupply.toNumber());
   });
 
+  it("Sells for an exact SOL amount", async () => {
+    const tokenBalance = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    const solAmount = new anchor.BN(0.01 * LAMPORTS_PER_SOL);
+    const maxTokensIn = new anchor.BN(tokenBalance.value.amount);
+
+    await program.methods
+      .sellExactOut(solAmount, maxTokensIn)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        seller: buyer.publicKey,
+        sellerTokenAccount: buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    const tokenBalanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+
+    // Seller receives exactly the requested amount, net of the fee
+    expect(buyerBalanceAfter - buyerBalanceBefore).to.equal(solAmount.toNumber());
+    expect(BigInt(tokenBalanceAfter.value.amount)).to.be.lessThan(BigInt(tokenBalance.value.amount));
+  });
+
+  it("Rejects an exact-out sell above max_tokens_in", async () => {
+    try {
+      await program.methods
+        .sellExactOut(new anchor.BN(0.01 * LAMPORTS_PER_SOL), new anchor.BN(1))
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          seller: buyer.publicKey,
+          sellerTokenAccount: buyerTokenAccount,
+          solVault,
+          creatorFeeVault,
+          feeCollector,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("SlippageExceeded");
+    }
+  });
+
+  it("Rejects a buy that pays its fee to another collector", async () => {
+    try {
+      await program.methods
+        .buy(new anchor.BN(0.01 * LAMPORTS_PER_SOL), new anchor.BN(0), false, null)
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          buyer: buyer.publicKey,
+          buyerTokenAccount,
+          solVault,
+          creatorFeeVault,
+          feeCollector: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidFeeCollector");
+    }
+  });
+
+  it("Rejects a sell that pays its fee to another collector", async () => {
+    try {
+      await program.methods
+        .sell(new anchor.BN(1_000_000_000), new anchor.BN(0))
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          seller: buyer.publicKey,
+          sellerTokenAccount: buyerTokenAccount,
+          solVault,
+          creatorFeeVault,
+          feeCollector: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidFeeCollector");
+    }
+  });
+
+  it("Registers a referral code", async () => {
+    referral = referralAddress(program.programId, referralCode);
+
+    await program.methods
+      .registerReferral(referralCode)
+      .accounts({
+        referral,
+        owner: seller.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([seller])
+      .rpc();
+
+    const referralAccount = await program.account.referral.fetch(referral);
+    expect(referralAccount.owner.toString()).to.equal(seller.publicKey.toString());
+    expect(referralAccount.code).to.equal(referralCode);
+    expect(referralAccount.referredVolume.toNumber()).to.equal(0);
+  });
+
+  it("Rejects a malformed referral code", async () => {
+    const code = "no spaces";
+    try {
+      await program.methods
+        .registerReferral(code)
+        .accounts({
+          referral: referralAddress(program.programId, code),
+          owner: seller.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidReferralCode");
+    }
+  });
+
+  it("Splits the fee between the platform, creator and referrer", async () => {
+    const config = await program.account.platformConfig.fetch(platformConfig);
+    const solAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+
+    const quote = await program.methods
+      .quoteBuy(solAmount, false)
+      .accounts({ bondingCurve, platformConfig })
+      .view();
+
+    const balances = async () =>
+      Promise.all(
+        [feeCollector, creatorFeeVault, referral].map((key) => provider.connection.getBalance(key))
+      );
+    const before = await balances();
+
+    await program.methods
+      .buy(solAmount, new anchor.BN(0), false, null)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        referral,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const after = await balances();
+    const fee = quote.fee.toNumber();
+    const creatorFee = Math.floor((fee * config.creatorFeeShareBps) / 10_000);
+    const referrerFee = Math.floor((fee * config.referrerFeeShareBps) / 10_000);
+
+    expect(after[1] - before[1]).to.equal(creatorFee);
+    expect(after[2] - before[2]).to.equal(referrerFee);
+    expect(after[0] - before[0]).to.equal(fee - creatorFee - referrerFee);
+
+    const referralAccount = await program.account.referral.fetch(referral);
+    expect(referralAccount.referredVolume.toString()).to.equal(solAmount.toString());
+    expect(referralAccount.feesEarned.toNumber()).to.equal(referrerFee);
+  });
+
+  it("Pays accrued referral fees to the referral owner", async () => {
+    try {
+      await program.methods
+        .claimReferralFees()
+        .accounts({ referral, owner: buyer.publicKey })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+
+    const { feesEarned } = await program.account.referral.fetch(referral);
+    const ownerBalanceBefore = await provider.connection.getBalance(seller.publicKey);
+
+    await program.methods
+      .claimReferralFees()
+      .accounts({ referral, owner: seller.publicKey })
+      .signers([seller])
+      .rpc();
+
+    const ownerBalanceAfter = await provider.connection.getBalance(seller.publicKey);
+    // The owner also pays the transaction fee
+    expect(ownerBalanceAfter - ownerBalanceBefore).to.be.within(
+      feesEarned.toNumber() - 10_000,
+      feesEarned.toNumber()
+    );
+  });
+
+  it("Rejects a trader referring themselves", async () => {
+    const ownCode = `b${Date.now().toString(36)}`;
+    const ownReferral = referralAddress(program.programId, ownCode);
+    await program.methods
+      .registerReferral(ownCode)
+      .accounts({
+        referral: ownReferral,
+        owner: buyer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    try {
+      await program.methods
+        .buy(new anchor.BN(0.01 * LAMPORTS_PER_SOL), new anchor.BN(0), false, null)
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          tokenMint,
+          buyer: buyer.publicKey,
+          buyerTokenAccount,
+          solVault,
+          creatorFeeVault,
+          feeCollector,
+          referral: ownReferral,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("SelfReferral");
+    }
+  });
+
+  it("Rejects a creator fee claim from anyone but the creator", async () => {
+    try {
+      await program.methods
+        .claimCreatorFees()
+        .accounts({ bondingCurve, creatorFeeVault, creator: buyer.publicKey })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+  });
+
+  it("Pays accrued creator fees to the creator", async () => {
+    const vaultInfo = await provider.connection.getAccountInfo(creatorFeeVault);
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(vaultInfo.data.length);
+    const accrued = vaultInfo.lamports - rent;
+    expect(accrued).to.be.greaterThan(0);
+
+    const creatorBalanceBefore = await provider.connection.getBalance(creator.publicKey);
+
+    await program.methods
+      .claimCreatorFees()
+      .accounts({ bondingCurve, creatorFeeVault, creator: creator.publicKey })
+      .signers([creator])
+      .rpc();
+
+    const creatorBalanceAfter = await provider.connection.getBalance(creator.publicKey);
+    expect(await provider.connection.getBalance(creatorFeeVault)).to.equal(rent);
+    // The creator also pays the transaction fee
+    expect(creatorBalanceAfter - creatorBalanceBefore).to.be.within(accrued - 10_000, accrued);
+
+    try {
+      await program.methods
+        .claimCreatorFees()
+        .accounts({ bondingCurve, creatorFeeVault, creator: creator.publicKey })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("NoFeesToClaim");
+    }
+  });
+
+  describe("treasury", () => {
+    const ops = Keypair.generate();
+    const buyback = Keypair.generate();
+    const recipients = [
+      { wallet: ops.publicKey, weight: 3 },
+      { wallet: buyback.publicKey, weight: 1 },
+    ];
+    const distributeAccounts = (wallets: PublicKey[]) =>
+      wallets.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }));
+
+    it("Rejects revenue recipients from a non-admin", async () => {
+      try {
+        await program.methods
+          .setRevenueRecipients(recipients)
+          .accounts({ platformConfig, treasury: feeCollector, admin: creator.publicKey })
+          .signers([creator])
+          .rpc();
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("Unauthorized");
+      }
+    });
+
+    it("Rejects a recipient list with a zero weight", async () => {
+      try {
+        await program.methods
+          .setRevenueRecipients([...recipients, { wallet: creator.publicKey, weight: 0 }])
+          .accounts({ platformConfig, treasury: feeCollector, admin: provider.wallet.publicKey })
+          .rpc();
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("InvalidRevenueRecipients");
+      }
+    });
+
+    it("Rejects a distribution to the wrong recipients", async () => {
+      await program.methods
+        .setRevenueRecipients(recipients)
+        .accounts({ platformConfig, treasury: feeCollector, admin: provider.wallet.publicKey })
+        .rpc();
+
+      try {
+        await program.methods
+          .distributeRevenue()
+          .accounts({ treasury: feeCollector })
+          .remainingAccounts(distributeAccounts([buyback.publicKey, ops.publicKey]))
+          .rpc();
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("InvalidRevenueRecipients");
+      }
+    });
+
+    it("Splits the treasury balance between its recipients by weight", async () => {
+      // Top up so each share clears the recipients' rent-exempt minimum
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          anchor.web3.SystemProgram.transfer({
+            fromPubkey: provider.wallet.publicKey,
+            toPubkey: feeCollector,
+            lamports: LAMPORTS_PER_SOL,
+          })
+        )
+      );
+
+      const treasuryInfo = await provider.connection.getAccountInfo(feeCollector);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(
+        treasuryInfo.data.length
+      );
+      const available = treasuryInfo.lamports - rent;
+      const { totalDistributed } = await program.account.treasury.fetch(feeCollector);
+
+      await program.methods
+        .distributeRevenue()
+        .accounts({ treasury: feeCollector })
+        .remainingAccounts(distributeAccounts([ops.publicKey, buyback.publicKey]))
+        .rpc();
+
+      const opsShare = Math.floor((available * 3) / 4);
+      const buybackShare = Math.floor(available / 4);
+      expect(await provider.connection.getBalance(ops.publicKey)).to.equal(opsShare);
+      expect(await provider.connection.getBalance(buyback.publicKey)).to.equal(buybackShare);
+      // Rounding dust stays behind for the next distribution
+      expect(await provider.connection.getBalance(feeCollector)).to.equal(
+        rent + available - opsShare - buybackShare
+      );
+
+      const treasuryAccount = await program.account.treasury.fetch(feeCollector);
+      expect(treasuryAccount.totalDistributed.toNumber()).to.equal(
+        totalDistributed.toNumber() + opsShare + buybackShare
+      );
+    });
+  });
+
+  it("Quotes exactly what a buy executes", async () => {
+    const solAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+
+    const quote = await program.methods
+      .quoteBuy(solAmount, false)
+      .accounts({ bondingCurve, platformConfig })
+      .view();
+
+    expect(quote.fee.toNumber()).to.equal(solAmount.toNumber() / 100);
+    expect(quote.priceAfter.gt(quote.priceBefore)).to.be.true;
+    expect(quote.hitsMaxSupply).to.be.false;
+    expect(quote.triggersGraduation).to.be.false;
+
+    const balanceBefore = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+
+    await program.methods
+      .buy(solAmount, quote.amountOut, false, null)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const balanceAfter = await provider.connection.getTokenAccountBalance(buyerTokenAccount);
+    const received = BigInt(balanceAfter.value.amount) - BigInt(balanceBefore.value.amount);
+    expect(received.toString()).to.equal(quote.amountOut.toString());
+
+    // Selling moves the price the other way
+    const sellQuote = await program.methods
+      .quoteSell(quote.amountOut)
+      .accounts({ bondingCurve, platformConfig })
+      .view();
+
+    expect(sellQuote.priceAfter.lt(sellQuote.priceBefore)).to.be.true;
+    expect(sellQuote.amountOut.add(sellQuote.fee).lte(solAmount)).to.be.true;
+
+    platformConfig,
+  });
+
+  it("Loses SOL on an immediate buy-sell round trip", async () => {
+    const solAmount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+
+    const buyQuote = await program.methods
+      .quoteBuy(solAmount, false)
+      .accounts({ bondingCurve, platformConfig })
+      .view();
+
+    await program.methods
+      .buy(solAmount, buyQuote.amountOut, false, null)
+      .accounts({
+        bondingCurve,
+        platformConfig,
+        tokenMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount,
+        solVault,
+        creatorFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const sellQuote = await program.methods
+      .quoteSell(buyQuote.amountOut)
+      .accounts({ bondingCurve, platformConfig })
+      .view();
+
+    expect(sellQuote.amountOut.lt(solAmount)).to.be.true;
+
+    // Vault keeps reserves plus its rent-exempt minimum
+    const curveAccount = await program.account.bondingCurve.fetch(bondingCurve);
+    const vaultBalance = await provider.connection.getBalance(solVault);
+    // SolVault account: discriminator + bump
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(9);
+    expect(vaultBalance).to.be.at.least(curveAccount.solReserves.toNumber() + rent);
+
+    platformConfig,
+  });
+
   it("Gets current price", async () => {
     const price = await program.methods
       .getPrice()
@@ -188,18 +1563,57 @@ describe("Bonding Curve", () => {
       })
       .view();
 
-    expect(price.toNumber()).to.be.greaterThan(BASE_PRICE.toNumber());
+    // Q64.64 lamports per whole token
+    expect(price.gt(BASE_PRICE.shln(64))).to.be.true;
+  });
+
+  it("Rejects withdrawing reserves before graduation", async () => {
+    try {
+      await program.methods
+        .withdrawReserves()
+        .accounts({
+          bondingCurve,
+          solVault,
+          recipient: creator.publicKey,
+          platformConfig,
+          graduationHandler: provider.wallet.publicKey,
+        })
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("NotGraduated");
+    }
+  });
+
+  it("Rejects graduation by an arbitrary signer", async () => {
+    try {
+      await program.methods
+        .graduate()
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          graduationHandler: creator.publicKey,
+        })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+
+    const curveAccount = await program.account.bondingCurve.fetch(bondingCurve);
+    expect(curveAccount.graduated).to.be.false;
   });
 
   it("Fails to buy after graduation", async () => {
-    // First graduate the curve
+    // First graduate the curve (the platform admin may stand in for the graduation program)
     await program.methods
       .graduate()
       .accounts({
         bondingCurve,
-        graduationHandler: creator.publicKey,
+        platformConfig,
+        graduationHandler: provider.wallet.publicKey,
       })
-      .signers([creator])
       .rpc();
 
     // Try to buy - should fail
@@ -208,14 +1622,16 @@ describe("Bonding Curve", () => {
 
     try {
       await program.methods
-        .buy(solAmount, minTokensOut)
+        .buy(solAmount, minTokensOut, false, null)
         .accounts({
           bondingCurve,
+          platformConfig,
           tokenMint,
           buyer: buyer.publicKey,
           buyerTokenAccount,
           solVault,
-          feeCollector: feeCollector.publicKey,
+          creatorFeeVault,
+          feeCollector,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
         })
@@ -230,13 +1646,7 @@ describe("Bonding Curve", () => {
 
   it("Enforces slippage protection on buy", async () => {
     // Create new curve for this test
-    const newMint = await createMint(
-      provider.connection,
-      creator,
-      creator.publicKey,
-      null,
-      9
-    );
+    const newMint = await createCurveMint(program, creator);
 
     const [newCurve] = PublicKey.findProgramAddressSync(
       [Buffer.from("bonding_curve"), newMint.toBuffer()],
@@ -249,13 +1659,16 @@ describe("Bonding Curve", () => {
     );
 
     await program.methods
-      .initializeCurve(BASE_PRICE, MAX_SUPPLY)
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY))
       .accounts({
         bondingCurve: newCurve,
+        platformConfig,
         tokenMint: newMint,
         creator: creator.publicKey,
-        feeCollector: feeCollector.publicKey,
         solVault: newVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, newMint),
+        treasury: feeCollector,
+        factoryConfig,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([creator])
@@ -273,14 +1686,16 @@ describe("Bonding Curve", () => {
 
     try {
       await program.methods
-        .buy(solAmount, minTokensOut)
+        .buy(solAmount, minTokensOut, false, null)
         .accounts({
           bondingCurve: newCurve,
+          platformConfig,
           tokenMint: newMint,
           buyer: buyer.publicKey,
           buyerTokenAccount: newBuyerTokenAccount.address,
           solVault: newVault,
-          feeCollector: feeCollector.publicKey,
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, newMint),
+          feeCollector,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
         })
@@ -292,4 +1707,611 @@ describe("Bonding Curve", () => {
       expect(err.message).to.include("SlippageExceeded");
     }
   });
+
+  it("Partially fills a buy past max supply and refunds the rest", async () => {
+    const smallMint = await createCurveMint(program, creator);
+
+    const [smallCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), smallMint.toBuffer()],
+      program.programId
+    );
+
+    const [smallVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), smallMint.toBuffer()],
+      program.programId
+    );
+
+    const smallMaxSupply = new anchor.BN("1000000000000"); // 1,000 tokens
+
+    await program.methods
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, smallMaxSupply))
+      .accounts({
+        bondingCurve: smallCurve,
+        platformConfig,
+        tokenMint: smallMint,
+        creator: creator.publicKey,
+        solVault: smallVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, smallMint),
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const smallBuyerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      buyer,
+      smallMint,
+      buyer.publicKey
+    );
+
+    const solAmount = new anchor.BN(1 * LAMPORTS_PER_SOL);
+
+    // Without allow_partial the quote itself refuses a fill past max_supply
+    try {
+      await program.methods
+        .quoteBuy(solAmount, false)
+        .accounts({ bondingCurve: smallCurve, platformConfig })
+        .view();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("MaxSupplyExceeded");
+    }
+
+    const quote = await program.methods
+      .quoteBuy(solAmount, true)
+      .accounts({ bondingCurve: smallCurve, platformConfig })
+      .view();
+
+    expect(quote.amountOut.toString()).to.equal(smallMaxSupply.toString());
+    expect(quote.hitsMaxSupply).to.be.true;
+    expect(quote.solRefunded.toNumber()).to.be.greaterThan(0);
+
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    await program.methods
+      .buy(solAmount, new anchor.BN(0), true, null)
+      .accounts({
+        bondingCurve: smallCurve,
+        platformConfig,
+        tokenMint: smallMint,
+        buyer: buyer.publicKey,
+        buyerTokenAccount: smallBuyerTokenAccount.address,
+        solVault: smallVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, smallMint),
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    const curveAccount = await program.account.bondingCurve.fetch(smallCurve);
+
+    expect(curveAccount.tokenSupply.toString()).to.equal(smallMaxSupply.toString());
+    expect(buyerBalanceBefore - buyerBalanceAfter).to.equal(
+      solAmount.sub(quote.solRefunded).toNumber()
+    );
+  });
+
+  it("Completes the curve when a buy crosses the graduation threshold", async () => {
+    const thMint = await createCurveMint(program, creator);
+    const [thCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), thMint.toBuffer()],
+      program.programId
+    );
+    const [thVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), thMint.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY))
+      .accounts({
+        bondingCurve: thCurve,
+        platformConfig,
+        tokenMint: thMint,
+        creator: creator.publicKey,
+        solVault: thVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, thMint),
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const thBuyerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      buyer,
+      thMint,
+      buyer.publicKey
+    );
+    const buyAccounts = {
+      bondingCurve: thCurve,
+      platformConfig,
+      tokenMint: thMint,
+      buyer: buyer.publicKey,
+      buyerTokenAccount: thBuyerTokenAccount.address,
+      solVault: thVault,
+      creatorFeeVault: creatorFeeVaultAddress(program.programId, thMint),
+      feeCollector,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    // Lower the shared threshold for this curve only; restored below
+    const { graduationThreshold } = await program.account.platformConfig.fetch(platformConfig);
+    const threshold = new anchor.BN(0.05 * LAMPORTS_PER_SOL);
+    await program.methods
+      .updateConfig({ ...NO_CONFIG_UPDATE, graduationThreshold: threshold })
+      .accounts({ platformConfig, admin: provider.wallet.publicKey })
+      .rpc();
+
+    try {
+      const solAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+      const quote = await program.methods
+        .quoteBuy(solAmount, false)
+        .accounts({ bondingCurve: thCurve, platformConfig })
+        .view();
+      expect(quote.triggersGraduation).to.be.true;
+      expect(quote.solRefunded.gtn(0)).to.be.true;
+
+      await program.methods
+        .buy(solAmount, new anchor.BN(0), false, null)
+        .accounts(buyAccounts)
+        .signers([buyer])
+        .rpc();
+
+      const curveAccount = await program.account.bondingCurve.fetch(thCurve);
+      expect(curveAccount.complete).to.be.true;
+      expect(curveAccount.solReserves.toString()).to.equal(threshold.toString());
+      expect(curveAccount.tokenSupply.toString()).to.equal(quote.amountOut.toString());
+
+      try {
+        await program.methods
+          .buy(new anchor.BN(0.01 * LAMPORTS_PER_SOL), new anchor.BN(0), false, null)
+          .accounts(buyAccounts)
+          .signers([buyer])
+          .rpc();
+        expect.fail("Should have thrown error");
+      } catch (err) {
+        expect(err.message).to.include("CurveComplete");
+      }
+    } finally {
+      await program.methods
+        .updateConfig({ ...NO_CONFIG_UPDATE, graduationThreshold })
+        .accounts({ platformConfig, admin: provider.wallet.publicKey })
+        .rpc();
+    }
+  });
+
+  it("Prices a constant product curve from its virtual reserves", async () => {
+    const cpMint = await createCurveMint(program, creator);
+
+    const [cpCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), cpMint.toBuffer()],
+      program.programId
+    );
+
+    const [cpVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), cpMint.toBuffer()],
+      program.programId
+    );
+
+    const virtualSolReserves = new anchor.BN(30 * LAMPORTS_PER_SOL);
+    const virtualTokenReserves = new anchor.BN("1073000000000000000");
+
+    await program.methods
+      .initializeCurve({
+        constantProduct: { virtualSolReserves, virtualTokenReserves },
+      }, curveParams(BASE_PRICE, MAX_SUPPLY))
+      .accounts({
+        bondingCurve: cpCurve,
+        platformConfig,
+        tokenMint: cpMint,
+        creator: creator.publicKey,
+        solVault: cpVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, cpMint),
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const cpAccount = await program.account.bondingCurve.fetch(cpCurve);
+    expect(cpAccount.virtualSolReserves.toString()).to.equal(virtualSolReserves.toString());
+    expect(cpAccount.virtualTokenReserves.toString()).to.equal(virtualTokenReserves.toString());
+
+    const price = await program.methods
+      .getPrice()
+      .accounts({ bondingCurve: cpCurve })
+      .view();
+
+    // x / y lamports per whole token, Q64.64
+    const expected = ((30n * 1_000_000_000n * TOKEN_PRECISION) << 64n) / 1_073_000_000_000_000_000n;
+    expect(price.toString()).to.equal(expected.toString());
+  });
+
+  it("Rejects an exponential curve without a doubling supply", async () => {
+    const expMint = await createCurveMint(program, creator);
+
+    const [expCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), expMint.toBuffer()],
+      program.programId
+    );
+
+    const [expVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), expMint.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializeCurve({
+          exponential: { doublingSupply: new anchor.BN(0) },
+        }, curveParams(BASE_PRICE, MAX_SUPPLY))
+        .accounts({
+          bondingCurve: expCurve,
+          platformConfig,
+          tokenMint: expMint,
+          creator: creator.publicKey,
+          solVault: expVault,
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, expMint),
+          treasury: feeCollector,
+          factoryConfig,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidCurveParams");
+    }
+  });
+
+  it("Starts a piecewise curve at its config's start price", async () => {
+    const pwMint = await createCurveMint(program, creator);
+
+    const [pwCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), pwMint.toBuffer()],
+      program.programId
+    );
+
+    const [pwVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), pwMint.toBuffer()],
+      program.programId
+    );
+
+    // sqrt(1e-5 lamports per base unit) in Q64.64, i.e. 10_000 lamports per token
+    const sqrtStartPrice = 58_333_726_687_135_162n;
+    const curveConfig = Keypair.generate();
+
+    await program.methods
+      .createCurveConfig(new anchor.BN(sqrtStartPrice.toString()), [
+        {
+          sqrtPrice: new anchor.BN((sqrtStartPrice * 2n).toString()),
+          liquidity: new anchor.BN("100000000000000000000000000000000000"),
+        },
+        {
+          sqrtPrice: new anchor.BN((sqrtStartPrice * 4n).toString()),
+          liquidity: new anchor.BN("200000000000000000000000000000000000"),
+        },
+      ])
+      .accounts({
+        curveConfig: curveConfig.publicKey,
+        authority: creator.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([curveConfig, creator])
+      .rpc();
+
+    await program.methods
+      .initializeCurve({
+        piecewise: { config: curveConfig.publicKey },
+      }, curveParams(BASE_PRICE, MAX_SUPPLY))
+      .accounts({
+        bondingCurve: pwCurve,
+        platformConfig,
+        tokenMint: pwMint,
+        creator: creator.publicKey,
+        solVault: pwVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, pwMint),
+        curveConfig: curveConfig.publicKey,
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const curveAccount = await program.account.bondingCurve.fetch(pwCurve);
+    expect(curveAccount.sqrtPrice.toString()).to.equal(sqrtStartPrice.toString());
+
+    const price = await program.methods
+      .getPrice()
+      .accounts({ bondingCurve: pwCurve })
+      .view();
+
+    const expected = (sqrtStartPrice * sqrtStartPrice * TOKEN_PRECISION) >> 64n;
+    expect(price.toString()).to.equal(expected.toString());
+
+    // Trading without the config account is rejected
+    const pwBuyerTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      buyer,
+      pwMint,
+      buyer.publicKey
+    );
+
+    try {
+      await program.methods
+        .buy(new anchor.BN(0.1 * LAMPORTS_PER_SOL), new anchor.BN(0), false, null)
+        .accounts({
+          bondingCurve: pwCurve,
+          platformConfig,
+          tokenMint: pwMint,
+          buyer: buyer.publicKey,
+          buyerTokenAccount: pwBuyerTokenAccount.address,
+          solVault: pwVault,
+          creatorFeeVault: creatorFeeVaultAddress(program.programId, pwMint),
+          feeCollector,
+          curveConfig: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("CurveConfigMismatch");
+    }
+  });
+
+  it("Walks a piecewise buy and sell across a segment boundary", async () => {
+    const pwMint = await createCurveMint(program, creator);
+
+    const [pwCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), pwMint.toBuffer()],
+      program.programId
+    );
+
+    const [pwVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), pwMint.toBuffer()],
+      program.programId
+    );
+
+    // sqrt(1e-10 lamports per base unit) in Q64.64. The first segment holds
+    // ~0.108 SOL, so a 0.2 SOL buy runs into the second.
+    const sqrtStartPrice = 184_467_440_737_095n;
+    const segments: [bigint, bigint][] = [
+      [sqrtStartPrice * 2n, 200_000_000_000_000_000_000_000_000_000_000n],
+      [sqrtStartPrice * 4n, 600_000_000_000_000_000_000_000_000_000_000n],
+    ];
+    const ranges = piecewiseRanges(sqrtStartPrice, segments);
+    const curveConfig = Keypair.generate();
+
+    await program.methods
+      .createCurveConfig(
+        new anchor.BN(sqrtStartPrice.toString()),
+        segments.map(([sqrtPrice, liquidity]) => ({
+          sqrtPrice: new anchor.BN(sqrtPrice.toString()),
+          liquidity: new anchor.BN(liquidity.toString()),
+        }))
+      )
+      .accounts({
+        curveConfig: curveConfig.publicKey,
+        authority: creator.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([curveConfig, creator])
+      .rpc();
+
+    await program.methods
+      .initializeCurve({
+        piecewise: { config: curveConfig.publicKey },
+      }, curveParams(BASE_PRICE, MAX_SUPPLY))
+      .accounts({
+        bondingCurve: pwCurve,
+        platformConfig,
+        tokenMint: pwMint,
+        creator: creator.publicKey,
+        solVault: pwVault,
+        creatorFeeVault: creatorFeeVaultAddress(program.programId, pwMint),
+        curveConfig: curveConfig.publicKey,
+        treasury: feeCollector,
+        factoryConfig,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+
+    const pwBuyerTokenAccount = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, buyer, pwMint, buyer.publicKey)
+    ).address;
+    const tradeAccounts = {
+      bondingCurve: pwCurve,
+      platformConfig,
+      tokenMint: pwMint,
+      solVault: pwVault,
+      creatorFeeVault: creatorFeeVaultAddress(program.programId, pwMint),
+      feeCollector,
+      curveConfig: curveConfig.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    const solAmount = 200_000_000n;
+    const net = solAmount - ceilDiv(solAmount * BigInt(FEE_BPS), 10_000n);
+    const bought = piecewiseBuy(ranges, sqrtStartPrice, net);
+    expect(bought.sqrtPrice > sqrtStartPrice * 2n).to.be.true;
+
+    await program.methods
+      .buy(new anchor.BN(solAmount.toString()), new anchor.BN(bought.tokens.toString()), false, null)
+      .accounts({ ...tradeAccounts, buyer: buyer.publicKey, buyerTokenAccount: pwBuyerTokenAccount })
+      .signers([buyer])
+      .rpc();
+
+    const minted = await provider.connection.getTokenAccountBalance(pwBuyerTokenAccount);
+    expect(minted.value.amount).to.equal(bought.tokens.toString());
+    let curveAccount = await program.account.bondingCurve.fetch(pwCurve);
+    expect(curveAccount.sqrtPrice.toString()).to.equal(bought.sqrtPrice.toString());
+    expect(curveAccount.solReserves.toString()).to.equal(net.toString());
+
+    // Sell back down into the first segment
+    const kept = 100_000_000_000_000_000n;
+    const sold = piecewiseSell(ranges, bought.sqrtPrice, bought.tokens - kept);
+    expect(sold.sqrtPrice < sqrtStartPrice * 2n).to.be.true;
+    const received = sold.sol - ceilDiv(sold.sol * BigInt(FEE_BPS), 10_000n);
+
+    const sellerBefore = await provider.connection.getBalance(buyer.publicKey);
+    await program.methods
+      .sell(new anchor.BN((bought.tokens - kept).toString()), new anchor.BN(received.toString()))
+      .accounts({ ...tradeAccounts, seller: buyer.publicKey, sellerTokenAccount: pwBuyerTokenAccount })
+      .signers([buyer])
+      .rpc();
+
+    const sellerAfter = await provider.connection.getBalance(buyer.publicKey);
+    expect(BigInt(sellerAfter - sellerBefore).toString()).to.equal(received.toString());
+    const left = await provider.connection.getTokenAccountBalance(pwBuyerTokenAccount);
+    expect(left.value.amount).to.equal(kept.toString());
+    curveAccount = await program.account.bondingCurve.fetch(pwCurve);
+    expect(curveAccount.sqrtPrice.toString()).to.equal(sold.sqrtPrice.toString());
+    expect(curveAccount.solReserves.toString()).to.equal((net - sold.sol).toString());
+  });
+
+  describe("exact round trips", () => {
+    const SOL_AMOUNT = 0.5 * LAMPORTS_PER_SOL;
+
+    // Buys SOL_AMOUNT on a fresh curve of `curveKind` and sells every token
+    // back, checking both legs against the off-chain formula. Only constant
+    // product curves have virtual reserves.
+    async function expectExactRoundTrip(
+      curveKind: object,
+      math: CurveMath,
+      virtualSol = 0n,
+      virtualTokens = 0n
+    ) {
+      const mint = await createCurveMint(program, creator);
+      const [curve] = PublicKey.findProgramAddressSync(
+        [Buffer.from("bonding_curve"), mint.toBuffer()],
+        program.programId
+      );
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_vault"), mint.toBuffer()],
+        program.programId
+      );
+      const curveFeeVault = creatorFeeVaultAddress(program.programId, mint);
+
+      await program.methods
+        .initializeCurve(curveKind, curveParams(BASE_PRICE, MAX_SUPPLY))
+        .accounts({
+          bondingCurve: curve,
+          platformConfig,
+          tokenMint: mint,
+          creator: creator.publicKey,
+          solVault: vault,
+          creatorFeeVault: curveFeeVault,
+          treasury: feeCollector,
+          factoryConfig,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+
+      const tokenAccount = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, buyer, mint, buyer.publicKey)
+      ).address;
+      const tradeAccounts = {
+        bondingCurve: curve,
+        platformConfig,
+        tokenMint: mint,
+        solVault: vault,
+        creatorFeeVault: curveFeeVault,
+        feeCollector,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      };
+
+      const gross = BigInt(SOL_AMOUNT);
+      const net = gross - ceilDiv(gross * BigInt(FEE_BPS), 10_000n);
+      const tokens = math.buyTokensOut({ supply: 0n, virtualSol, virtualTokens }, net);
+
+      const buyerBefore = await provider.connection.getBalance(buyer.publicKey);
+      await program.methods
+        .buy(new anchor.BN(SOL_AMOUNT), new anchor.BN(tokens.toString()), false, null)
+        .accounts({ ...tradeAccounts, buyer: buyer.publicKey, buyerTokenAccount: tokenAccount })
+        .signers([buyer])
+        .rpc();
+
+      const minted = await provider.connection.getTokenAccountBalance(tokenAccount);
+      expect(minted.value.amount).to.equal(tokens.toString());
+      expect(buyerBefore - (await provider.connection.getBalance(buyer.publicKey))).to.equal(SOL_AMOUNT);
+      let curveAccount = await program.account.bondingCurve.fetch(curve);
+      expect(curveAccount.tokenSupply.toString()).to.equal(tokens.toString());
+      expect(curveAccount.solReserves.toString()).to.equal(net.toString());
+
+      const solOut = math.sellSolOut(
+        { supply: tokens, virtualSol: virtualSol + net, virtualTokens: virtualTokens - tokens },
+        tokens
+      );
+      const received = solOut - ceilDiv(solOut * BigInt(FEE_BPS), 10_000n);
+
+      const sellerBefore = await provider.connection.getBalance(buyer.publicKey);
+      await program.methods
+        .sell(new anchor.BN(tokens.toString()), new anchor.BN(received.toString()))
+        .accounts({ ...tradeAccounts, seller: buyer.publicKey, sellerTokenAccount: tokenAccount })
+        .signers([buyer])
+        .rpc();
+
+      const sellerAfter = await provider.connection.getBalance(buyer.publicKey);
+      expect(BigInt(sellerAfter - sellerBefore).toString()).to.equal(received.toString());
+      curveAccount = await program.account.bondingCurve.fetch(curve);
+      expect(curveAccount.tokenSupply.toString()).to.equal("0");
+      expect(curveAccount.solReserves.toString()).to.equal((net - solOut).toString());
+    }
+
+    it("Round trips a linear curve", async () => {
+      await expectExactRoundTrip(
+        { linear: {} },
+        polynomialMath(2n, BigInt(MAX_SUPPLY.toString()), BigInt(BASE_PRICE.toString()))
+      );
+    });
+
+    it("Round trips a quadratic curve", async () => {
+      await expectExactRoundTrip(
+        { quadratic: {} },
+        polynomialMath(3n, BigInt(MAX_SUPPLY.toString()), BigInt(BASE_PRICE.toString()))
+      );
+    });
+
+    it("Round trips an exponential curve", async () => {
+      const doublingSupply = 200_000_000_000_000_000n; // price doubles every 200M tokens
+      await expectExactRoundTrip(
+        { exponential: { doublingSupply: new anchor.BN(doublingSupply.toString()) } },
+        exponentialMath(doublingSupply, BigInt(BASE_PRICE.toString()))
+      );
+    });
+
+    it("Round trips a constant product curve", async () => {
+      const virtualSol = 30n * 1_000_000_000n;
+      const virtualTokens = 1_073_000_000_000_000_000n;
+      await expectExactRoundTrip(
+        {
+          constantProduct: {
+            virtualSolReserves: new anchor.BN(virtualSol.toString()),
+            virtualTokenReserves: new anchor.BN(virtualTokens.toString()),
+          },
+        },
+        constantProductMath,
+        virtualSol,
+        virtualTokens
+      );
+    });
+  });
 });
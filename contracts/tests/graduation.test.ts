@@ -2,6 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Graduation } from "../target/types/graduation";
 import { BondingCurve } from "../target/types/bonding_curve";
+import { TokenFactory } from "../target/types/token_factory";
 import { PublicKey, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { 
   TOKEN_PROGRAM_ID,
@@ -9,6 +10,14 @@ import {
   getOrCreateAssociatedTokenAccount,
 } from "@solana/spl-token";
 import { expect } from "chai";
+import {
+  createCurveMint,
+  creatorFeeVaultAddress,
+  curveParams,
+  ensureFactoryConfig,
+  ensurePlatformConfig,
+  POST_GRADUATION_FEE_BPS,
+} from "./helpers";
 
 describe("Graduation", () => {
   const provider = anchor.AnchorProvider.env();
@@ -20,13 +29,26 @@ describe("Graduation", () => {
   let tokenMint: PublicKey;
   let bondingCurve: PublicKey;
   let solVault: PublicKey;
+  let creatorFeeVault: PublicKey;
+  let platformConfig: PublicKey;
+  let graduationAuthority: PublicKey;
+  let feeCollector: PublicKey;
+  let factoryConfig: PublicKey;
   
   const creator = Keypair.generate();
   const authority = Keypair.generate();
-  const feeCollector = Keypair.generate();
   
   const BASE_PRICE = new anchor.BN(100_000);
-  const MAX_SUPPLY = new anchor.BN(1_000_000_000);
+  const MAX_SUPPLY = new anchor.BN("1000000000000000000");
+
+  const RAYDIUM_CP_SWAP = new PublicKey("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+  // CP-Swap AmmConfig fixtures loaded by Anchor.toml, at the curves' 0.25%
+  // post-graduation fee and at 1%
+  const AMM_CONFIG_25_BPS = new PublicKey("4yuf7B5RUxXJUfyh8Pu7t3Jt1d2DQFmmukAkCuB8JpCF");
+  const AMM_CONFIG_100_BPS = new PublicKey("9YJBx95jtwDhpjDkJNMNX421MDbF11VjSDVdphcusWSd");
+  // CP-Swap pools created with each of them (tests/fixtures)
+  const POOL_25_BPS = new PublicKey("GfkoZE5DeTs6Z5x4FLYGHWnntvwKoJt6L38WDFGv5XkL");
+  const POOL_100_BPS = new PublicKey("CSVDZUTKhB7kziwBVbo6d4fVgFYP3W7RvVSPjrysrnjJ");
 
   before(async () => {
     await provider.connection.requestAirdrop(
@@ -40,15 +62,20 @@ describe("Graduation", () => {
     
     await new Promise(resolve => setTimeout(resolve, 1000));
 
-    // Create mint and bonding curve
-    tokenMint = await createMint(
-      provider.connection,
-      creator,
-      creator.publicKey,
-      null,
-      9
+    ({ platformConfig, feeCollector } = await ensurePlatformConfig(bondingCurveProgram));
+    factoryConfig = await ensureFactoryConfig(
+      anchor.workspace.TokenFactory as Program<TokenFactory>,
+      platformConfig
     );
 
+    [graduationAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("graduation_authority")],
+      graduationProgram.programId
+    );
+
+    // Create mint and bonding curve
+    tokenMint = await createCurveMint(bondingCurveProgram, creator);
+
     [bondingCurve] = PublicKey.findProgramAddressSync(
       [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
       bondingCurveProgram.programId
@@ -58,32 +85,41 @@ describe("Graduation", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       bondingCurveProgram.programId
     );
+    creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram.programId, tokenMint);
 
     // Initialize bonding curve
     await bondingCurveProgram.methods
-      .initializeCurve(BASE_PRICE, MAX_SUPPLY)
+      .initializeCurve({ quadratic: {} }, curveParams(BASE_PRICE, MAX_SUPPLY))
       .accounts({
         bondingCurve,
+        platformConfig,
         tokenMint,
         creator: creator.publicKey,
-        feeCollector: feeCollector.publicKey,
         solVault,
+        creatorFeeVault,
+        treasury: feeCollector,
+        factoryConfig,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([creator])
       .rpc();
   });
 
+  it("Records the graduation authority PDA in the platform config", async () => {
+    const config = await bondingCurveProgram.account.platformConfig.fetch(platformConfig);
+    expect(config.graduationAuthority.toBase58()).to.equal(graduationAuthority.toBase58());
+  });
+
   it("Fails graduation check when threshold not reached", async () => {
     try {
       await graduationProgram.methods
         .checkAndGraduate()
         .accounts({
           bondingCurve,
-          authority: authority.publicKey,
+          platformConfig,
+          graduationAuthority,
           bondingCurveProgram: bondingCurveProgram.programId,
         })
-        .signers([authority])
         .rpc();
       
       expect.fail("Should have thrown error");
@@ -92,6 +128,42 @@ describe("Graduation", () => {
     }
   });
 
+  it("Rejects a bonding curve account not owned by the bonding curve program", async () => {
+    try {
+      await graduationProgram.methods
+        .checkAndGraduate()
+        .accounts({
+          bondingCurve: creator.publicKey,
+          platformConfig,
+          graduationAuthority,
+          bondingCurveProgram: bondingCurveProgram.programId,
+        })
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("AccountOwnedByWrongProgram");
+    }
+  });
+
+  it("Rejects a spoofed bonding curve program", async () => {
+    try {
+      await graduationProgram.methods
+        .checkAndGraduate()
+        .accounts({
+          bondingCurve,
+          platformConfig,
+          graduationAuthority,
+          bondingCurveProgram: graduationProgram.programId,
+        })
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidProgramId");
+    }
+  });
+
   it("Graduates token when threshold reached", async () => {
     // Buy enough tokens to reach graduation threshold
     // Need 690 SOL in reserves for $69K (assuming 1 SOL = $100)
@@ -114,29 +186,37 @@ describe("Graduation", () => {
     const minTokensOut = new anchor.BN(0);
 
     await bondingCurveProgram.methods
-      .buy(solAmount, minTokensOut)
+      .buy(solAmount, minTokensOut, false, null)
       .accounts({
         bondingCurve,
+        platformConfig,
         tokenMint,
         buyer: buyer.publicKey,
         buyerTokenAccount: buyerTokenAccount.address,
         solVault,
-        feeCollector: feeCollector.publicKey,
+        creatorFeeVault,
+        feeCollector,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([buyer])
       .rpc();
 
+    // The buy stops at the threshold and completes the curve
+    const completed = await bondingCurveProgram.account.bondingCurve.fetch(bondingCurve);
+    const config = await bondingCurveProgram.account.platformConfig.fetch(platformConfig);
+    expect(completed.complete).to.be.true;
+    expect(completed.solReserves.toString()).to.equal(config.graduationThreshold.toString());
+
     // Now check and graduate
     const tx = await graduationProgram.methods
       .checkAndGraduate()
       .accounts({
         bondingCurve,
-        authority: authority.publicKey,
+        platformConfig,
+        graduationAuthority,
         bondingCurveProgram: bondingCurveProgram.programId,
       })
-      .signers([authority])
       .rpc();
 
     const curveAccount = await bondingCurveProgram.account.bondingCurve.fetch(bondingCurve);
@@ -149,10 +229,10 @@ describe("Graduation", () => {
         .checkAndGraduate()
         .accounts({
           bondingCurve,
-          authority: authority.publicKey,
+          platformConfig,
+          graduationAuthority,
           bondingCurveProgram: bondingCurveProgram.programId,
         })
-        .signers([authority])
         .rpc();
       
       expect.fail("Should have thrown error");
@@ -161,32 +241,111 @@ describe("Graduation", () => {
     }
   });
 
-  it("Migrates liquidity to Raydium", async () => {
+  it("Rejects migration with accounts that don't belong to the curve", async () => {
     const tokenVault = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      tokenMint,
+      graduationAuthority,
+      true
+    );
+    const otherMint = await createMint(provider.connection, creator, creator.publicKey, null, 9);
+    const otherMintVault = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      otherMint,
+      graduationAuthority,
+      true
+    );
+    const walletVault = await getOrCreateAssociatedTokenAccount(
       provider.connection,
       authority,
       tokenMint,
       authority.publicKey
     );
+    const [otherSolVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), otherMint.toBuffer()],
+      bondingCurveProgram.programId
+    );
+
+    const migrateAccounts = {
+      bondingCurve,
+      tokenMint,
+      solVault,
+      tokenVault: tokenVault.address,
+      graduationAuthority,
+      raydiumPool: POOL_25_BPS,
+      ammConfig: AMM_CONFIG_25_BPS,
+      raydiumProgram: RAYDIUM_CP_SWAP,
+      authority: authority.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+    const spoofs: [string, Partial<typeof migrateAccounts>, string][] = [
+      ["a vault the graduation authority doesn't own", { tokenVault: walletVault.address }, "ConstraintTokenOwner"],
+      ["a vault of another mint", { tokenVault: otherMintVault.address }, "ConstraintTokenMint"],
+      ["another mint", { tokenMint: otherMint }, "ConstraintAddress"],
+      ["another curve's SOL vault", { solVault: otherSolVault }, "ConstraintSeeds"],
+      ["a fee config CP-Swap doesn't own", { ammConfig: platformConfig }, "ConstraintOwner"],
+      ["a pool CP-Swap doesn't own", { raydiumPool: Keypair.generate().publicKey }, "ConstraintOwner"],
+      ["a pool created with another fee config", { raydiumPool: POOL_100_BPS }, "PoolConfigMismatch"],
+      ["a fake Raydium program", { raydiumProgram: Keypair.generate().publicKey }, "ConstraintAddress"],
+    ];
 
-    const raydiumPool = Keypair.generate();
-    const raydiumProgram = new PublicKey("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+    for (const [what, spoof, error] of spoofs) {
+      try {
+        await graduationProgram.methods
+          .migrateToRaydium()
+          .accounts({ ...migrateAccounts, ...spoof })
+          .signers([authority])
+          .rpc();
+        expect.fail(`Should have rejected ${what}`);
+      } catch (err) {
+        expect(err.message, what).to.include(error);
+      }
+    }
+  });
+
+  it("Migrates liquidity to Raydium", async () => {
+    const tokenVault = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      tokenMint,
+      graduationAuthority,
+      true
+    );
 
     const curveAccountBefore = await bondingCurveProgram.account.bondingCurve.fetch(bondingCurve);
+    expect(curveAccountBefore.postGraduationFeeBps).to.equal(POST_GRADUATION_FEE_BPS);
+    const migrateAccounts = {
+      bondingCurve,
+      tokenMint,
+      solVault,
+      tokenVault: tokenVault.address,
+      graduationAuthority,
+      raydiumPool: POOL_25_BPS,
+      ammConfig: AMM_CONFIG_25_BPS,
+      raydiumProgram: RAYDIUM_CP_SWAP,
+      authority: authority.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    // The pool must use the fee tier recorded on the curve
+    try {
+      await graduationProgram.methods
+        .migrateToRaydium()
+        .accounts({ ...migrateAccounts, raydiumPool: POOL_100_BPS, ammConfig: AMM_CONFIG_100_BPS })
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("PoolFeeMismatch");
+    }
 
     const tx = await graduationProgram.methods
       .migrateToRaydium()
-      .accounts({
-        bondingCurve,
-        tokenMint,
-        solVault,
-        tokenVault: tokenVault.address,
-        raydiumPool: raydiumPool.publicKey,
-        raydiumProgram,
-        authority: authority.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: anchor.web3.SystemProgram.programId,
-      })
+      .accounts(migrateAccounts)
       .signers([authority])
       .rpc();
 
@@ -195,9 +354,76 @@ describe("Graduation", () => {
     expect(tx).to.be.a('string');
   });
 
+  it("Rejects an emergency withdrawal to someone other than the creator", async () => {
+    try {
+      await graduationProgram.methods
+        .emergencyWithdraw()
+        .accounts({
+          bondingCurve,
+          solVault,
+          creator: authority.publicKey,
+          platformConfig,
+          admin: provider.wallet.publicKey,
+          graduationAuthority,
+          bondingCurveProgram: bondingCurveProgram.programId,
+        })
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintAddress");
+    }
+  });
+
+  it("Rejects an emergency withdrawal from another curve's vault", async () => {
+    const [otherVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), Keypair.generate().publicKey.toBuffer()],
+      bondingCurveProgram.programId
+    );
+
+    try {
+      await graduationProgram.methods
+        .emergencyWithdraw()
+        .accounts({
+          bondingCurve,
+          solVault: otherVault,
+          creator: creator.publicKey,
+          platformConfig,
+          admin: provider.wallet.publicKey,
+          graduationAuthority,
+          bondingCurveProgram: bondingCurveProgram.programId,
+        })
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Rejects an emergency withdrawal by a non-admin", async () => {
+    try {
+      await graduationProgram.methods
+        .emergencyWithdraw()
+        .accounts({
+          bondingCurve,
+          solVault,
+          creator: creator.publicKey,
+          platformConfig,
+          admin: authority.publicKey,
+          graduationAuthority,
+          bondingCurveProgram: bondingCurveProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+  });
+
   it("Handles emergency withdrawal", async () => {
-    const admin = authority; // Using authority as admin for test
-    
     const creatorBalanceBefore = await provider.connection.getBalance(creator.publicKey);
 
     const tx = await graduationProgram.methods
@@ -206,14 +432,20 @@ describe("Graduation", () => {
         bondingCurve,
         solVault,
         creator: creator.publicKey,
-        admin: admin.publicKey,
+        platformConfig,
+        admin: provider.wallet.publicKey,
+        graduationAuthority,
+        bondingCurveProgram: bondingCurveProgram.programId,
       })
-      .signers([admin])
       .rpc();
 
     const creatorBalanceAfter = await provider.connection.getBalance(creator.publicKey);
     
     // Creator should have received SOL back
     expect(creatorBalanceAfter).to.be.greaterThan(creatorBalanceBefore);
+
+    // Reserves are paid out through the bonding curve program
+    const curveAfter = await bondingCurveProgram.account.bondingCurve.fetch(bondingCurve);
+    expect(curveAfter.solReserves.toNumber()).to.equal(0);
   });
 });
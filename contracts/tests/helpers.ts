@@ -0,0 +1,570 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { BondingCurve } from "../target/types/bonding_curve";
+import { TokenFactory } from "../target/types/token_factory";
+import {
+  PublicKey,
+  Keypair,
+  LAMPORTS_PER_SOL,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  MINT_SIZE,
+  AccountLayout,
+  createAssociatedTokenAccountIdempotentInstruction,
+  createInitializeMint2Instruction,
+  createMint,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { BankrunProvider } from "anchor-bankrun";
+import { Clock, start } from "solana-bankrun";
+import { createHash } from "crypto";
+import { readFileSync } from "fs";
+
+export const PLATFORM_MAX_FEE_BPS = 200; // 2%
+export const FEE_BPS = 100; // 1%, buy and sell fee of the test curves
+export const CREATOR_FEE_SHARE_BPS = 2_000; // 20% of each fee
+export const REFERRER_FEE_SHARE_BPS = 1_000; // 10% of each fee
+export const POST_GRADUATION_FEE_BPS = 25; // 0.25% on the migrated pool
+export const GRADUATION_THRESHOLD = new anchor.BN(690 * LAMPORTS_PER_SOL);
+export const CREATION_FEE = new anchor.BN(0.02 * LAMPORTS_PER_SOL); // token factory fee per curve
+
+// ConfigUpdate that changes nothing; spread it and set the fields to change
+export const NO_CONFIG_UPDATE = {
+  admin: null,
+  maxFeeBps: null,
+  creatorFeeShareBps: null,
+  referrerFeeShareBps: null,
+  postGraduationFeeBps: null,
+  graduationThreshold: null,
+  minBasePrice: null,
+  maxBasePrice: null,
+  paused: null,
+};
+
+// CurveParams for initialize_curve / create_token, with the test curves
+// fees and no launch fee, launch limits or allowlist unless overridden
+export function curveParams(
+  basePrice: anchor.BN,
+  maxSupply: anchor.BN,
+  overrides: {
+    buyFeeBps?: number;
+    sellFeeBps?: number;
+    launchFee?: object | null;
+    launchLimits?: object | null;
+    allowlist?: object | null;
+  } = {}
+) {
+  return {
+    basePrice,
+    maxSupply,
+    buyFeeBps: FEE_BPS,
+    sellFeeBps: FEE_BPS,
+    launchFee: null,
+    launchLimits: null,
+    allowlist: null,
+    ...overrides,
+  };
+}
+
+export function platformConfigAddress(programId: PublicKey): PublicKey {
+  const [platformConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("platform_config")],
+    programId
+  );
+  return platformConfig;
+}
+
+export function treasuryAddress(programId: PublicKey): PublicKey {
+  const [treasury] = PublicKey.findProgramAddressSync([Buffer.from("treasury")], programId);
+  return treasury;
+}
+
+const BPF_LOADER_UPGRADEABLE = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+
+// ProgramData account of an upgradeable program, which records its upgrade
+// authority
+export function programDataAddress(programId: PublicKey): PublicKey {
+  const [programData] = PublicKey.findProgramAddressSync(
+    [programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE
+  );
+  return programData;
+}
+
+export function bondingCurveAddress(programId: PublicKey, tokenMint: PublicKey): PublicKey {
+  const [bondingCurve] = PublicKey.findProgramAddressSync(
+    [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
+    programId
+  );
+  return bondingCurve;
+}
+
+// A fresh 9-decimal mint whose mint authority is the PDA of the curve that
+// will be created for it, as buys mint with the curve's signature
+export async function createCurveMint(
+  program: Program<BondingCurve>,
+  payer: Keypair
+): Promise<PublicKey> {
+  const mint = Keypair.generate();
+  return createMint(
+    program.provider.connection,
+    payer,
+    bondingCurveAddress(program.programId, mint.publicKey),
+    null,
+    9,
+    mint
+  );
+}
+
+export function creatorFeeVaultAddress(programId: PublicKey, tokenMint: PublicKey): PublicKey {
+  const [creatorFeeVault] = PublicKey.findProgramAddressSync(
+    [Buffer.from("creator_fee_vault"), tokenMint.toBuffer()],
+    programId
+  );
+  return creatorFeeVault;
+}
+
+export function referralAddress(programId: PublicKey, code: string): PublicKey {
+  const [referral] = PublicKey.findProgramAddressSync(
+    [Buffer.from("referral"), Buffer.from(code)],
+    programId
+  );
+  return referral;
+}
+
+export function walletPurchasesAddress(
+  programId: PublicKey,
+  tokenMint: PublicKey,
+  wallet: PublicKey
+): PublicKey {
+  const [walletPurchases] = PublicKey.findProgramAddressSync(
+    [Buffer.from("wallet_purchases"), tokenMint.toBuffer(), wallet.toBuffer()],
+    programId
+  );
+  return walletPurchases;
+}
+
+function sha256(...parts: Buffer[]): Buffer {
+  return createHash("sha256").update(Buffer.concat(parts)).digest();
+}
+
+// Leaf of a wallet in an allowlist tree, mirrors AllowlistPhase::leaf
+export function allowlistLeaf(wallet: PublicKey, allocation: anchor.BN | null): Buffer {
+  const parts = [Buffer.from([0]), wallet.toBuffer()];
+  if (allocation) parts.push(allocation.toArrayLike(Buffer, "le", 8));
+  return sha256(...parts);
+}
+
+// Merkle tree over allowlist entries with sorted-pair inner nodes, as
+// AllowlistPhase::verify expects. An unpaired node moves up unchanged.
+export function allowlistTree(entries: { wallet: PublicKey; allocation: anchor.BN | null }[]) {
+  const layers = [entries.map(({ wallet, allocation }) => allowlistLeaf(wallet, allocation))];
+  while (layers[layers.length - 1].length > 1) {
+    const layer = layers[layers.length - 1];
+    const next: Buffer[] = [];
+    for (let i = 0; i < layer.length; i += 2) {
+      if (i + 1 === layer.length) {
+        next.push(layer[i]);
+      } else {
+        const [low, high] = [layer[i], layer[i + 1]].sort(Buffer.compare);
+        next.push(sha256(Buffer.from([1]), low, high));
+      }
+    }
+    layers.push(next);
+  }
+
+  return {
+    root: [...layers[layers.length - 1][0]],
+    // Proof for the entry at `index`, as passed to buy
+    proof(index: number): number[][] {
+      const proof: number[][] = [];
+      for (const layer of layers.slice(0, -1)) {
+        const sibling = index ^ 1;
+        if (sibling < layer.length) proof.push([...layer[sibling]]);
+        index >>= 1;
+      }
+      return proof;
+    },
+  };
+}
+
+// ConfigParams every test file shares
+export const CONFIG_PARAMS = {
+  maxFeeBps: PLATFORM_MAX_FEE_BPS,
+  creatorFeeShareBps: CREATOR_FEE_SHARE_BPS,
+  referrerFeeShareBps: REFERRER_FEE_SHARE_BPS,
+  postGraduationFeeBps: POST_GRADUATION_FEE_BPS,
+  graduationThreshold: GRADUATION_THRESHOLD,
+  minBasePrice: new anchor.BN(1),
+  maxBasePrice: new anchor.BN(LAMPORTS_PER_SOL),
+};
+
+export function initializeConfigAccounts(program: Program<BondingCurve>, admin: PublicKey) {
+  return {
+    platformConfig: platformConfigAddress(program.programId),
+    treasury: treasuryAddress(program.programId),
+    admin,
+    program: program.programId,
+    programData: programDataAddress(program.programId),
+    systemProgram: anchor.web3.SystemProgram.programId,
+  };
+}
+
+// The platform config is a singleton shared by every test file, so the
+// first file to run creates it with the provider wallet as admin. Its fee
+// collector is the treasury, created alongside it.
+export async function ensurePlatformConfig(
+  program: Program<BondingCurve>
+): Promise<{ platformConfig: PublicKey; feeCollector: PublicKey }> {
+  const provider = program.provider as anchor.AnchorProvider;
+  const platformConfig = platformConfigAddress(program.programId);
+
+  let config = await program.account.platformConfig.fetchNullable(platformConfig);
+  if (config === null) {
+    await program.methods
+      .initializeConfig(CONFIG_PARAMS)
+      .accounts(initializeConfigAccounts(program, provider.wallet.publicKey))
+      .rpc();
+    config = await program.account.platformConfig.fetch(platformConfig);
+  }
+
+  return { platformConfig, feeCollector: config.feeCollector };
+}
+
+export function factoryConfigAddress(programId: PublicKey): PublicKey {
+  const [factoryConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("factory_config")],
+    programId
+  );
+  return factoryConfig;
+}
+
+// Every initialize_curve reads its creation fee from the token factory
+// config, so whichever suite runs first creates it
+export async function ensureFactoryConfig(
+  program: Program<TokenFactory>,
+  platformConfig: PublicKey
+): Promise<PublicKey> {
+  const provider = program.provider as anchor.AnchorProvider;
+  const factoryConfig = factoryConfigAddress(program.programId);
+
+  const config = await program.account.factoryConfig.fetchNullable(factoryConfig);
+  if (config === null) {
+    await program.methods
+      .initializeFactoryConfig(CREATION_FEE)
+      .accounts({
+        factoryConfig,
+        platformConfig,
+        admin: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  return factoryConfig;
+}
+
+export type LaunchCurve = {
+  bondingCurve: PublicKey;
+  tokenMint: PublicKey;
+  solVault: PublicKey;
+  creatorFeeVault: PublicKey;
+};
+
+// Program and ProgramData accounts of `elf` deployed at `programId` by the
+// upgradeable loader, with `authority` as upgrade authority
+function upgradeableProgramAccounts(programId: PublicKey, elf: Buffer, authority: PublicKey) {
+  const programData = programDataAddress(programId);
+
+  // UpgradeableLoaderState::Program
+  const program = Buffer.alloc(36);
+  program.writeUInt32LE(2, 0);
+  programData.toBuffer().copy(program, 4);
+
+  // UpgradeableLoaderState::ProgramData, deployed at slot 0
+  const header = Buffer.alloc(45);
+  header.writeUInt32LE(3, 0);
+  header.writeUInt8(1, 12);
+  authority.toBuffer().copy(header, 13);
+
+  return [
+    {
+      address: programId,
+      info: { lamports: LAMPORTS_PER_SOL, data: program, owner: BPF_LOADER_UPGRADEABLE, executable: true },
+    },
+    {
+      address: programData,
+      info: {
+        lamports: 100 * LAMPORTS_PER_SOL,
+        data: Buffer.concat([header, elf]),
+        owner: BPF_LOADER_UPGRADEABLE,
+        executable: false,
+      },
+    },
+  ];
+}
+
+// The token factory's config as the factory would have created it. Only its
+// creation fee is read, by initialize_curve, so the factory itself isn't
+// deployed.
+function factoryConfigAccount(programId: PublicKey) {
+  const [address, bump] = PublicKey.findProgramAddressSync(
+    [Buffer.from("factory_config")],
+    programId
+  );
+  const data = Buffer.alloc(17);
+  createHash("sha256").update("account:FactoryConfig").digest().copy(data, 0, 0, 8);
+  data.writeBigUInt64LE(BigInt(CREATION_FEE.toString()), 8);
+  data.writeUInt8(bump, 16);
+
+  return {
+    address,
+    info: { lamports: LAMPORTS_PER_SOL, data, owner: programId, executable: false },
+  };
+}
+
+function fundedAccount(address: PublicKey) {
+  return {
+    address,
+    info: {
+      lamports: 100 * LAMPORTS_PER_SOL,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    },
+  };
+}
+
+// Bankrun bank with the bonding curve deployed and the platform config
+// created, for the suites that need to move the clock. Launch windows are
+// warped past instead of waited out.
+export async function launchFixture() {
+  const workspaceProgram = anchor.workspace.BondingCurve as Program<BondingCurve>;
+  const tokenFactoryId = (anchor.workspace.TokenFactory as Program<TokenFactory>).programId;
+  const admin = Keypair.generate();
+  const creator = Keypair.generate();
+  const buyer = Keypair.generate();
+
+  const context = await start(
+    [],
+    [
+      ...upgradeableProgramAccounts(
+        workspaceProgram.programId,
+        readFileSync("target/deploy/bonding_curve.so"),
+        admin.publicKey
+      ),
+      factoryConfigAccount(tokenFactoryId),
+      ...[admin, creator, buyer].map(({ publicKey }) => fundedAccount(publicKey)),
+    ]
+  );
+  const program = new Program<BondingCurve>(workspaceProgram.idl, new BankrunProvider(context));
+  const platformConfig = platformConfigAddress(program.programId);
+
+  // The bank's payer pays the transaction fees, so the signers' balances
+  // only move by what the instructions transfer. Sent directly rather than
+  // with rpc() so a failure carries the program logs, which name the error.
+  async function send(instructions: TransactionInstruction[], signers: Keypair[]) {
+    const tx = new Transaction().add(...instructions);
+    [tx.recentBlockhash] = await context.banksClient.getLatestBlockhash();
+    tx.feePayer = context.payer.publicKey;
+    tx.sign(context.payer, ...signers);
+
+    const { result, meta } = await context.banksClient.tryProcessTransaction(tx);
+    if (result) throw new Error([result, ...(meta?.logMessages ?? [])].join("\n"));
+  }
+
+  await send(
+    [
+      await program.methods
+        .initializeConfig(CONFIG_PARAMS)
+        .accounts(initializeConfigAccounts(program, admin.publicKey))
+        .instruction(),
+    ],
+    [admin]
+  );
+  const { feeCollector } = await program.account.platformConfig.fetch(platformConfig);
+
+  // A curve created by `creator` with `params`, on a fresh mint
+  async function createCurve(params: ReturnType<typeof curveParams>): Promise<LaunchCurve> {
+    const mint = Keypair.generate();
+    const curve = {
+      bondingCurve: bondingCurveAddress(program.programId, mint.publicKey),
+      tokenMint: mint.publicKey,
+      solVault: PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_vault"), mint.publicKey.toBuffer()],
+        program.programId
+      )[0],
+      creatorFeeVault: creatorFeeVaultAddress(program.programId, mint.publicKey),
+    };
+
+    const rent = await context.banksClient.getRent();
+    await send(
+      [
+        SystemProgram.createAccount({
+          fromPubkey: creator.publicKey,
+          newAccountPubkey: mint.publicKey,
+          lamports: Number(rent.minimumBalance(BigInt(MINT_SIZE))),
+          space: MINT_SIZE,
+          programId: TOKEN_PROGRAM_ID,
+        }),
+        // The curve mints on buys, so it has to hold the mint authority
+        createInitializeMint2Instruction(mint.publicKey, 9, curve.bondingCurve, null),
+        await program.methods
+          .initializeCurve({ quadratic: {} }, params)
+          .accounts({
+            ...curve,
+            platformConfig,
+            creator: creator.publicKey,
+            treasury: feeCollector,
+            factoryConfig: factoryConfigAddress(tokenFactoryId),
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+      ],
+      [creator, mint]
+    );
+    return curve;
+  }
+
+  // Creates the wallet's token account for the curve's mint if needed
+  function tokenAccountFor(curve: LaunchCurve, wallet: Keypair) {
+    const address = getAssociatedTokenAddressSync(curve.tokenMint, wallet.publicKey);
+    const instruction = createAssociatedTokenAccountIdempotentInstruction(
+      context.payer.publicKey,
+      address,
+      wallet.publicKey,
+      curve.tokenMint
+    );
+    return { address, instruction };
+  }
+
+  async function buy(curve: LaunchCurve, wallet: Keypair, solAmount: anchor.BN) {
+    const tokenAccount = tokenAccountFor(curve, wallet);
+    await send(
+      [
+        tokenAccount.instruction,
+        await program.methods
+          .buy(solAmount, new anchor.BN(0), false, null)
+          .accounts({
+            ...curve,
+            platformConfig,
+            buyer: wallet.publicKey,
+            buyerTokenAccount: tokenAccount.address,
+            feeCollector,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+      ],
+      [wallet]
+    );
+  }
+
+  async function buyExact(
+    curve: LaunchCurve,
+    wallet: Keypair,
+    tokenAmount: anchor.BN,
+    proof: object | null,
+    walletPurchases: PublicKey | null
+  ) {
+    const tokenAccount = tokenAccountFor(curve, wallet);
+    await send(
+      [
+        tokenAccount.instruction,
+        await program.methods
+          .buyExactOut(tokenAmount, new anchor.BN(LAMPORTS_PER_SOL), proof)
+          .accounts({
+            ...curve,
+            platformConfig,
+            buyer: wallet.publicKey,
+            buyerTokenAccount: tokenAccount.address,
+            feeCollector,
+            walletPurchases,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+      ],
+      [wallet]
+    );
+  }
+
+  // Creates the wallet's purchases account for the curve, returning its
+  // address
+  async function initializeWalletPurchases(curve: LaunchCurve, wallet: Keypair): Promise<PublicKey> {
+    const walletPurchases = walletPurchasesAddress(program.programId, curve.tokenMint, wallet.publicKey);
+    await send(
+      [
+        await program.methods
+          .initializeWalletPurchases()
+          .accounts({
+            bondingCurve: curve.bondingCurve,
+            walletPurchases,
+            wallet: wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .instruction(),
+      ],
+      [wallet]
+    );
+    return walletPurchases;
+  }
+
+  async function balance(address: PublicKey): Promise<number> {
+    return Number(await context.banksClient.getBalance(address));
+  }
+
+  // Tokens of the curve's mint the wallet holds, 0 before its first buy
+  async function tokenBalance(curve: LaunchCurve, wallet: Keypair): Promise<bigint> {
+    const account = await context.banksClient.getAccount(tokenAccountFor(curve, wallet).address);
+    return account === null ? 0n : AccountLayout.decode(account.data).amount;
+  }
+
+  // Warping records a new blockhash, so a repeated transaction afterwards
+  // isn't rejected as already processed
+  async function warpSlots(slots: number) {
+    const { slot } = await context.banksClient.getClock();
+    context.warpToSlot(slot + BigInt(slots));
+  }
+
+  // Moves to the next slot as well, so the same transaction can be sent
+  // on either side of a deadline
+  async function warpSeconds(secs: number) {
+    const { unixTimestamp } = await context.banksClient.getClock();
+    await warpSlots(1);
+    const clock = await context.banksClient.getClock();
+    context.setClock(
+      new Clock(
+        clock.slot,
+        clock.epochStartTimestamp,
+        clock.epoch,
+        clock.leaderScheduleEpoch,
+        unixTimestamp + BigInt(secs)
+      )
+    );
+  }
+
+  return {
+    context,
+    program,
+    creator,
+    buyer,
+    platformConfig,
+    feeCollector,
+    send,
+    createCurve,
+    buy,
+    buyExact,
+    initializeWalletPurchases,
+    balance,
+    tokenBalance,
+    warpSlots,
+    warpSeconds,
+  };
+}
+
+export type LaunchFixture = Awaited<ReturnType<typeof launchFixture>>;
@@ -0,0 +1,317 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Pump } from "../target/types/pump";
+import { BondingCurve } from "../target/types/bonding_curve";
+import { PublicKey, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { ensurePlatformConfig, treasuryAddress } from "./helpers";
+
+describe("Pump", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.Pump as Program<Pump>;
+  const bondingCurveProgram = anchor.workspace.BondingCurve as Program<BondingCurve>;
+
+  const lp = Keypair.generate();
+  const other = Keypair.generate();
+
+  const [dexConfiguration] = PublicKey.findProgramAddressSync(
+    [Buffer.from("CurveConfiguration")],
+    program.programId
+  );
+  const [globalAccount] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global")],
+    program.programId
+  );
+
+  const treasury = treasuryAddress(bondingCurveProgram.programId);
+
+  const TOKEN_LIQUIDITY = new anchor.BN("1000000000000"); // 1,000 tokens (9 decimals)
+  const SOL_LIQUIDITY = new anchor.BN(LAMPORTS_PER_SOL);
+
+  function poolAddress(mint: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("liquidity_pool"), mint.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  function liquidityProviderAddress(pool: PublicKey, user: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("LiqudityProvider"), pool.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // A fresh mint with a pool seeded by `lp`, who keeps the rest of the supply
+  async function seededPool(): Promise<PublicKey> {
+    const mint = await createMint(provider.connection, lp, lp.publicKey, null, 9);
+    const userTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      lp,
+      mint,
+      lp.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      lp,
+      mint,
+      userTokenAccount.address,
+      lp,
+      BigInt(TOKEN_LIQUIDITY.muln(10).toString())
+    );
+
+    const pool = poolAddress(mint);
+    await program.methods
+      .addLiquidity(TOKEN_LIQUIDITY, SOL_LIQUIDITY)
+      .accounts({
+        pool,
+        globalAccount,
+        liquidityProviderAccount: liquidityProviderAddress(pool, lp.publicKey),
+        mintTokenOne: mint,
+        poolTokenAccountOne: getAssociatedTokenAddressSync(mint, globalAccount, true),
+        userTokenAccountOne: userTokenAccount.address,
+        user: lp.publicKey,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([lp])
+      .rpc();
+
+    return mint;
+  }
+
+  function removeLiquidityAccounts(mint: PublicKey, user: PublicKey) {
+    const pool = poolAddress(mint);
+    return {
+      pool,
+      globalAccount,
+      liquidityProviderAccount: liquidityProviderAddress(pool, user),
+      coinMint: mint,
+      poolTokenAccountOne: getAssociatedTokenAddressSync(mint, globalAccount, true),
+      userTokenAccountOne: getAssociatedTokenAddressSync(mint, user),
+      user,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+  }
+
+  function swapAccounts(mint: PublicKey, user: PublicKey) {
+    return {
+      dexConfigurationAccount: dexConfiguration,
+      pool: poolAddress(mint),
+      globalAccount,
+      treasury,
+      mintTokenOne: mint,
+      poolTokenAccountOne: getAssociatedTokenAddressSync(mint, globalAccount, true),
+      userTokenAccountOne: getAssociatedTokenAddressSync(mint, user),
+      user,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+  }
+
+  let mint: PublicKey;
+  let otherMint: PublicKey;
+  let swapMint: PublicKey;
+
+  before(async () => {
+    for (const wallet of [lp, other]) {
+      const sig = await provider.connection.requestAirdrop(wallet.publicKey, 10 * LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig);
+    }
+
+    // Swap fees go to the bonding curve program's treasury
+    await ensurePlatformConfig(bondingCurveProgram);
+
+    const config = await program.account.curveConfiguration.fetchNullable(dexConfiguration);
+    if (config === null) {
+      await program.methods
+        .initialize(1)
+        .accounts({
+          dexConfigurationAccount: dexConfiguration,
+          globalAccount,
+          admin: provider.wallet.publicKey,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    mint = await seededPool();
+    otherMint = await seededPool();
+    swapMint = await seededPool();
+    await getOrCreateAssociatedTokenAccount(provider.connection, other, mint, other.publicKey);
+  });
+
+  it("Returns a provider's share of both reserves", async () => {
+    const pool = poolAddress(mint);
+    const accounts = removeLiquidityAccounts(mint, lp.publicKey);
+    const poolBefore = await program.account.liquidityPool.fetch(pool);
+    const { shares } = await program.account.liquidityProvider.fetch(accounts.liquidityProviderAccount);
+    const tokensBefore = (await getAccount(provider.connection, accounts.userTokenAccountOne)).amount;
+    const globalBefore = await provider.connection.getBalance(globalAccount);
+
+    const burned = shares.divn(2);
+    await program.methods
+      .removeLiquidity(burned)
+      .accounts(accounts)
+      .signers([lp])
+      .rpc();
+
+    const tokensOut = burned.mul(poolBefore.reserveOne).div(poolBefore.totalSupply);
+    const solOut = burned.mul(poolBefore.reserveTwo).div(poolBefore.totalSupply);
+
+    const poolAfter = await program.account.liquidityPool.fetch(pool);
+    const lpAfter = await program.account.liquidityProvider.fetch(accounts.liquidityProviderAccount);
+    const tokensAfter = (await getAccount(provider.connection, accounts.userTokenAccountOne)).amount;
+    const globalAfter = await provider.connection.getBalance(globalAccount);
+
+    expect(lpAfter.shares.toString()).to.equal(shares.sub(burned).toString());
+    expect(poolAfter.totalSupply.toString()).to.equal(poolBefore.totalSupply.sub(burned).toString());
+    expect(poolAfter.reserveOne.toString()).to.equal(poolBefore.reserveOne.sub(tokensOut).toString());
+    expect(poolAfter.reserveTwo.toString()).to.equal(poolBefore.reserveTwo.sub(solOut).toString());
+    expect((tokensAfter - tokensBefore).toString()).to.equal(tokensOut.toString());
+    expect(globalBefore - globalAfter).to.equal(solOut.toNumber());
+  });
+
+  it("Rejects removing liquidity with another provider's LP account", async () => {
+    try {
+      await program.methods
+        .removeLiquidity(new anchor.BN(1))
+        .accounts({
+          ...removeLiquidityAccounts(mint, other.publicKey),
+          liquidityProviderAccount: removeLiquidityAccounts(mint, lp.publicKey)
+            .liquidityProviderAccount,
+        })
+        .signers([other])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Rejects paying out of another pool's token account", async () => {
+    try {
+      await program.methods
+        .removeLiquidity(new anchor.BN(1))
+        .accounts({
+          ...removeLiquidityAccounts(mint, lp.publicKey),
+          poolTokenAccountOne: getAssociatedTokenAddressSync(otherMint, globalAccount, true),
+        })
+        .signers([lp])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintAssociated");
+    }
+  });
+
+  it("Rejects removing liquidity from another mint's pool", async () => {
+    try {
+      await program.methods
+        .removeLiquidity(new anchor.BN(1))
+        .accounts({
+          ...removeLiquidityAccounts(mint, lp.publicKey),
+          pool: poolAddress(otherMint),
+        })
+        .signers([lp])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Rejects burning more shares than the provider holds", async () => {
+    const accounts = removeLiquidityAccounts(mint, lp.publicKey);
+    const { shares } = await program.account.liquidityProvider.fetch(accounts.liquidityProviderAccount);
+
+    try {
+      await program.methods
+        .removeLiquidity(shares.addn(1))
+        .accounts(accounts)
+        .signers([lp])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InsufficientShares");
+    }
+  });
+
+  // The provider wallet pays the transaction fees, so `lp`'s balance only
+  // moves by what the swap transfers
+  it("Sends the fee on SOL paid in to the treasury", async () => {
+    const pool = poolAddress(swapMint);
+    const amountIn = new anchor.BN(LAMPORTS_PER_SOL / 10);
+    const poolBefore = await program.account.liquidityPool.fetch(pool);
+    const userBefore = await provider.connection.getBalance(lp.publicKey);
+    const globalBefore = await provider.connection.getBalance(globalAccount);
+    const treasuryBefore = await provider.connection.getBalance(treasury);
+
+    await program.methods
+      .swap(amountIn, new anchor.BN(0))
+      .accounts(swapAccounts(swapMint, lp.publicKey))
+      .signers([lp])
+      .rpc();
+
+    const poolAfter = await program.account.liquidityPool.fetch(pool);
+    const userAfter = await provider.connection.getBalance(lp.publicKey);
+    const globalAfter = await provider.connection.getBalance(globalAccount);
+    const treasuryAfter = await provider.connection.getBalance(treasury);
+
+    const { fees } = await program.account.curveConfiguration.fetch(dexConfiguration);
+    const toPool = poolAfter.reserveTwo.sub(poolBefore.reserveTwo).toNumber();
+    const fee = treasuryAfter - treasuryBefore;
+
+    expect(userBefore - userAfter).to.equal(amountIn.toNumber());
+    expect(globalAfter - globalBefore).to.equal(toPool);
+    expect(fee).to.equal(amountIn.toNumber() - toPool);
+    expect(fee).to.be.closeTo((amountIn.toNumber() * fees) / 100, 2);
+  });
+
+  it("Sends the fee on SOL paid out to the treasury", async () => {
+    const pool = poolAddress(swapMint);
+    const amountIn = TOKEN_LIQUIDITY.divn(10);
+    const poolBefore = await program.account.liquidityPool.fetch(pool);
+    const userBefore = await provider.connection.getBalance(lp.publicKey);
+    const globalBefore = await provider.connection.getBalance(globalAccount);
+    const treasuryBefore = await provider.connection.getBalance(treasury);
+
+    await program.methods
+      .swap(amountIn, new anchor.BN(1))
+      .accounts(swapAccounts(swapMint, lp.publicKey))
+      .signers([lp])
+      .rpc();
+
+    const poolAfter = await program.account.liquidityPool.fetch(pool);
+    const userAfter = await provider.connection.getBalance(lp.publicKey);
+    const globalAfter = await provider.connection.getBalance(globalAccount);
+    const treasuryAfter = await provider.connection.getBalance(treasury);
+
+    const { fees } = await program.account.curveConfiguration.fetch(dexConfiguration);
+    const grossOut = poolBefore.reserveTwo.sub(poolAfter.reserveTwo).toNumber();
+    const fee = treasuryAfter - treasuryBefore;
+
+    expect(poolAfter.reserveOne.sub(poolBefore.reserveOne).toString()).to.equal(amountIn.toString());
+    expect(globalBefore - globalAfter).to.equal(grossOut);
+    expect(fee).to.be.greaterThan(0);
+    expect(userAfter - userBefore).to.equal(grossOut - fee);
+    expect(fee).to.be.closeTo((grossOut * fees) / 100, 2);
+  });
+});
@@ -1,9 +1,18 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { TokenFactory } from "../target/types/token_factory";
+import { BondingCurve } from "../target/types/bonding_curve";
 import { PublicKey, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { expect } from "chai";
+import {
+  CREATION_FEE,
+  creatorFeeVaultAddress,
+  curveParams,
+  ensureFactoryConfig,
+  ensurePlatformConfig,
+  treasuryAddress,
+} from "./helpers";
 
 describe("Token Factory", () => {
   const provider = anchor.AnchorProvider.env();
@@ -12,14 +21,62 @@ describe("Token Factory", () => {
   const program = anchor.workspace.TokenFactory as Program<TokenFactory>;
   
   const creator = Keypair.generate();
-  const feeCollector = Keypair.generate();
+  const partner = Keypair.generate();
+  let platformConfig: PublicKey;
+  let factoryConfig: PublicKey;
+  let treasury: PublicKey;
+  
+  const METADATA_PROGRAM = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+  // Every account create_token needs for a fresh mint, partner entry aside
+  function createTokenAccounts(tokenMint: PublicKey, tokenCreator: PublicKey) {
+    const bondingCurveProgram = anchor.workspace.BondingCurve.programId;
+    return {
+      mint: tokenMint,
+      mintAuthority: PublicKey.findProgramAddressSync(
+        [Buffer.from("mint_authority"), tokenMint.toBuffer()],
+        program.programId
+      )[0],
+      metadata: PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), METADATA_PROGRAM.toBuffer(), tokenMint.toBuffer()],
+        METADATA_PROGRAM
+      )[0],
+      creator: tokenCreator,
+      bondingCurve: PublicKey.findProgramAddressSync(
+        [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
+        bondingCurveProgram
+      )[0],
+      solVault: PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_vault"), tokenMint.toBuffer()],
+        bondingCurveProgram
+      )[0],
+      creatorFeeVault: creatorFeeVaultAddress(bondingCurveProgram, tokenMint),
+      platformConfig,
+      factoryConfig,
+      treasury,
+      bondingCurveProgram,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    };
+  }
 
   before(async () => {
     await provider.connection.requestAirdrop(
       creator.publicKey,
       10 * LAMPORTS_PER_SOL
     );
+    await provider.connection.requestAirdrop(
+      partner.publicKey,
+      10 * LAMPORTS_PER_SOL
+    );
     await new Promise(resolve => setTimeout(resolve, 1000));
+
+    const bondingCurveProgram = anchor.workspace.BondingCurve as Program<BondingCurve>;
+    ({ platformConfig } = await ensurePlatformConfig(bondingCurveProgram));
+    treasury = treasuryAddress(bondingCurveProgram.programId);
+
+    factoryConfig = await ensureFactoryConfig(program, platformConfig);
   });
 
   it("Creates a new token with metadata and bonding curve", async () => {
@@ -51,6 +108,7 @@ describe("Token Factory", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       bondingCurveProgram
     );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
 
     const name = "Test Token";
     const symbol = "TEST";
@@ -58,8 +116,10 @@ describe("Token Factory", () => {
     const basePrice = new anchor.BN(100_000);
     const maxSupply = new anchor.BN(1_000_000_000);
 
+    const treasuryBalanceBefore = await provider.connection.getBalance(treasury);
+
     const tx = await program.methods
-      .createToken(name, symbol, uri, basePrice, maxSupply)
+      .createToken(name, symbol, uri, curveParams(basePrice, maxSupply))
       .accounts({
         mint: tokenMint,
         mintAuthority,
@@ -67,7 +127,10 @@ describe("Token Factory", () => {
         creator: creator.publicKey,
         bondingCurve,
         solVault,
-        feeCollector: feeCollector.publicKey,
+        creatorFeeVault,
+        platformConfig,
+        factoryConfig,
+        treasury,
         bondingCurveProgram,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -87,6 +150,75 @@ describe("Token Factory", () => {
     // Verify bonding curve was initialized (via CPI)
     const bondingCurveAccount = await provider.connection.getAccountInfo(bondingCurve);
     expect(bondingCurveAccount).to.not.be.null;
+
+    // The creation fee went to the treasury
+    const treasuryBalanceAfter = await provider.connection.getBalance(treasury);
+    expect(treasuryBalanceAfter - treasuryBalanceBefore).to.equal(CREATION_FEE.toNumber());
+  });
+
+  it("Rejects a creation fee change from a non-admin", async () => {
+    try {
+      await program.methods
+        .setCreationFee(new anchor.BN(0))
+        .accounts({ factoryConfig, platformConfig, admin: creator.publicKey })
+        .signers([creator])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("Unauthorized");
+    }
+  });
+
+  it("Waives the creation fee for allowlisted partners", async () => {
+    const [partnerEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("partner"), partner.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .addPartner(partner.publicKey)
+      .accounts({
+        partner: partnerEntry,
+        platformConfig,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const mintKeypair = Keypair.generate();
+    const treasuryBalanceBefore = await provider.connection.getBalance(treasury);
+
+    await program.methods
+      .createToken("Partner Token", "PART", "https://example.com/token.json", curveParams(new anchor.BN(100_000), new anchor.BN(1_000_000_000)))
+      .accounts({
+        ...createTokenAccounts(mintKeypair.publicKey, partner.publicKey),
+        partner: partnerEntry,
+      })
+      .signers([partner, mintKeypair])
+      .rpc();
+
+    expect(await provider.connection.getBalance(treasury)).to.equal(treasuryBalanceBefore);
+  });
+
+  it("Rejects another wallet's partner entry", async () => {
+    const [partnerEntry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("partner"), partner.publicKey.toBuffer()],
+      program.programId
+    );
+    const mintKeypair = Keypair.generate();
+
+    try {
+      await program.methods
+        .createToken("Test Token", "TEST", "https://example.com/token.json", curveParams(new anchor.BN(100_000), new anchor.BN(1_000_000_000)))
+        .accounts({
+          ...createTokenAccounts(mintKeypair.publicKey, creator.publicKey),
+          partner: partnerEntry,
+        })
+        .signers([creator, mintKeypair])
+        .rpc();
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
   });
 
   it("Validates token name length", async () => {
@@ -116,6 +248,7 @@ describe("Token Factory", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       bondingCurveProgram
     );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
 
     const name = "A".repeat(33); // Too long
     const symbol = "TEST";
@@ -125,7 +258,7 @@ describe("Token Factory", () => {
 
     try {
       await program.methods
-        .createToken(name, symbol, uri, basePrice, maxSupply)
+        .createToken(name, symbol, uri, curveParams(basePrice, maxSupply))
         .accounts({
           mint: tokenMint,
           mintAuthority,
@@ -133,7 +266,10 @@ describe("Token Factory", () => {
           creator: creator.publicKey,
           bondingCurve,
           solVault,
-          feeCollector: feeCollector.publicKey,
+          creatorFeeVault,
+          platformConfig,
+          factoryConfig,
+          treasury,
           bondingCurveProgram,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -175,6 +311,7 @@ describe("Token Factory", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       bondingCurveProgram
     );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
 
     const name = "Test Token";
     const symbol = "VERYLONGSYMBOL"; // Too long
@@ -184,7 +321,7 @@ describe("Token Factory", () => {
 
     try {
       await program.methods
-        .createToken(name, symbol, uri, basePrice, maxSupply)
+        .createToken(name, symbol, uri, curveParams(basePrice, maxSupply))
         .accounts({
           mint: tokenMint,
           mintAuthority,
@@ -192,7 +329,10 @@ describe("Token Factory", () => {
           creator: creator.publicKey,
           bondingCurve,
           solVault,
-          feeCollector: feeCollector.publicKey,
+          creatorFeeVault,
+          platformConfig,
+          factoryConfig,
+          treasury,
           bondingCurveProgram,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -234,6 +374,7 @@ describe("Token Factory", () => {
       [Buffer.from("sol_vault"), tokenMint.toBuffer()],
       bondingCurveProgram
     );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
 
     const name = "Test Token";
     const symbol = "TEST";
@@ -243,7 +384,7 @@ describe("Token Factory", () => {
 
     try {
       await program.methods
-        .createToken(name, symbol, uri, basePrice, maxSupply)
+        .createToken(name, symbol, uri, curveParams(basePrice, maxSupply))
         .accounts({
           mint: tokenMint,
           mintAuthority,
@@ -251,7 +392,10 @@ describe("Token Factory", () => {
           creator: creator.publicKey,
           bondingCurve,
           solVault,
-          feeCollector: feeCollector.publicKey,
+          creatorFeeVault,
+          platformConfig,
+          factoryConfig,
+          treasury,
           bondingCurveProgram,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -265,4 +409,122 @@ describe("Token Factory", () => {
       expect(err.message).to.include("InvalidBasePrice");
     }
   });
+
+  it("Rejects a bonding curve account that is not the mint's PDA", async () => {
+    const mintKeypair = Keypair.generate();
+    const tokenMint = mintKeypair.publicKey;
+
+    const [mintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), tokenMint.toBuffer()],
+      program.programId
+    );
+
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("metadata"),
+        new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").toBuffer(),
+        tokenMint.toBuffer(),
+      ],
+      new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s")
+    );
+
+    const bondingCurveProgram = anchor.workspace.BondingCurve.programId;
+    const [bondingCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
+      bondingCurveProgram
+    );
+    const [solVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), tokenMint.toBuffer()],
+      bondingCurveProgram
+    );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
+
+    try {
+      await program.methods
+        .createToken("Test Token", "TEST", "https://example.com/token.json", curveParams(new anchor.BN(100_000), new anchor.BN(1_000_000_000)))
+        .accounts({
+          mint: tokenMint,
+          mintAuthority,
+          metadata,
+          creator: creator.publicKey,
+          // Curve PDA of some other mint
+          bondingCurve: PublicKey.findProgramAddressSync(
+            [Buffer.from("bonding_curve"), Keypair.generate().publicKey.toBuffer()],
+            bondingCurveProgram
+          )[0],
+          solVault,
+          creatorFeeVault,
+          platformConfig,
+          factoryConfig,
+          treasury,
+          bondingCurveProgram,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([creator, mintKeypair])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("ConstraintSeeds");
+    }
+  });
+
+  it("Rejects a spoofed bonding curve program", async () => {
+    const mintKeypair = Keypair.generate();
+    const tokenMint = mintKeypair.publicKey;
+
+    const [mintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), tokenMint.toBuffer()],
+      program.programId
+    );
+
+    const [metadata] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("metadata"),
+        new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").toBuffer(),
+        tokenMint.toBuffer(),
+      ],
+      new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s")
+    );
+
+    const bondingCurveProgram = anchor.workspace.BondingCurve.programId;
+    const [bondingCurve] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bonding_curve"), tokenMint.toBuffer()],
+      bondingCurveProgram
+    );
+    const [solVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), tokenMint.toBuffer()],
+      bondingCurveProgram
+    );
+    const creatorFeeVault = creatorFeeVaultAddress(bondingCurveProgram, tokenMint);
+
+    try {
+      await program.methods
+        .createToken("Test Token", "TEST", "https://example.com/token.json", curveParams(new anchor.BN(100_000), new anchor.BN(1_000_000_000)))
+        .accounts({
+          mint: tokenMint,
+          mintAuthority,
+          metadata,
+          creator: creator.publicKey,
+          bondingCurve,
+          solVault,
+          creatorFeeVault,
+          platformConfig,
+          factoryConfig,
+          treasury,
+          bondingCurveProgram: program.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([creator, mintKeypair])
+        .rpc();
+
+      expect.fail("Should have thrown error");
+    } catch (err) {
+      expect(err.message).to.include("InvalidProgramId");
+    }
+  });
 });